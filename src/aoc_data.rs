@@ -5,66 +5,605 @@ pub use self::aoc_data::*;
 #[export_module]
 mod aoc_data {
     use super::*;
+    use crate::{
+        dynamic_image::{DynamicImage, SharedDynImg},
+        line::Line,
+    };
+    use rhai::Map;
     use std::{
         cell::RefCell,
+        collections::HashMap,
+        fmt,
         fs::File,
-        io::{BufRead, BufReader},
+        io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom},
     };
 
-    type SharedAocData = Shared<Locked<AocData>>;
+    pub type SharedAocData = Shared<Locked<AocData>>;
+
+    /// Files and in-memory data can both be read again from any offset
+    trait DataReader: BufRead + Seek {}
+
+    impl<R: BufRead + Seek> DataReader for R {}
+
+    /// How far the data has been read
+    #[derive(Clone, Copy, Default)]
+    struct ReadPos {
+        /// Number of lines read so far
+        line_no: usize,
+        /// Byte offset of the next line from the start of this data. Sections and cases have
+        /// their own text with `\n` line breaks, so for them it is not an offset in the file.
+        offset: u64,
+    }
 
     pub struct AocData {
         path: PathBuf,
-        file: BufReader<File>,
+        file: Box<dyn DataReader>,
+        pos: ReadPos,
+        /// Number of the line before the first one in the file, for sections of a file
+        line_base: usize,
+        /// Line being processed in the file, `None` before the first line and after the end.
+        /// Shared with the sections so errors point at the line read last from any of them.
+        processing: Shared<Locked<Option<usize>>>,
+        /// Byte offset of each line, only built once random access is needed
+        line_starts: Option<Vec<u64>>,
+        /// The next line with its line break, once `peek_line` has read it ahead
+        peeked: Option<Vec<u8>>,
     }
 
     impl AocData {
+        fn new(path: PathBuf, file: Box<dyn DataReader>) -> Self {
+            Self {
+                path,
+                file,
+                pos: ReadPos::default(),
+                line_base: 0,
+                processing: Default::default(),
+                line_starts: None,
+                peeked: None,
+            }
+        }
+
         pub fn load(path: PathBuf) -> Result<SharedAocData> {
             let file = File::open(&path)
                 .map(BufReader::new)
                 .wrap_err_with(|| format!("Failed open data file {path:?}"))?;
 
-            Ok(Shared::new(RefCell::new(Self { path, file })))
+            Ok(Shared::new(RefCell::new(Self::new(path, Box::new(file)))))
         }
 
-        pub fn read_line(&mut self) -> Option<ImmutableString> {
-            let mut ret = String::new();
-            let actual = self
-                .file
-                .read_line(&mut ret)
-                .wrap_err_with(|| format!("Failed to read the next line from {:?}", self.path))
-                .unwrap(); // ToDo: Fallible iterator support?
+        /// Serve data that is already in memory, `path` is only used in error messages.
+        /// `line_base` is the number of lines in front of `text` in that file.
+        pub fn from_text(path: PathBuf, text: String, line_base: usize) -> SharedAocData {
+            let mut data = Self::new(path, Box::new(Cursor::new(text.into_bytes())));
+            data.line_base = line_base;
+            Shared::new(RefCell::new(data))
+        }
+
+        /// Read the lines up to the next blank line into a reader of their own, skipping
+        /// leading blank lines like `read_paragraph`
+        fn read_section(&mut self) -> RhaiRes<Option<SharedAocData>> {
+            let mut text = Vec::new();
+            let mut line_base = 0;
+            while let Some(line) = self.read_blob()? {
+                if !line.is_empty() {
+                    if text.is_empty() {
+                        line_base = self.line_base + self.pos.line_no - 1;
+                    }
+                    text.extend(line);
+                    text.push(b'\n');
+                } else if !text.is_empty() {
+                    break;
+                }
+            }
+            if text.is_empty() {
+                return Ok(None);
+            }
+            let mut section = Self::new(self.path.clone(), Box::new(Cursor::new(text)));
+            section.line_base = line_base;
+            section.processing = self.processing.clone();
+            Ok(Some(Shared::new(RefCell::new(section))))
+        }
+
+        /// Count the line that was just read, an error still uses up its line
+        fn count_line(&mut self, res: io::Result<usize>) -> RhaiRes<bool> {
+            let ret = match res {
+                Ok(0) => {
+                    *self.processing.borrow_mut() = None;
+                    return Ok(false);
+                }
+                Ok(len) => {
+                    self.pos.offset += len as u64;
+                    Ok(true)
+                }
+                Err(e) => {
+                    if let Ok(offset) = self.file.stream_position() {
+                        self.pos.offset = offset;
+                    }
+                    Err(e)
+                }
+            };
+            self.pos.line_no += 1;
+            let line_no = self.line_base + self.pos.line_no;
+            *self.processing.borrow_mut() = Some(line_no);
+            ret.map_err(|e| self.read_error(format!("line {line_no}"), e))
+        }
+
+        /// `path:line` of the line being processed, `None` before the first line and
+        /// after the end of the data
+        pub fn location(&self) -> Option<String> {
+            self.processing
+                .borrow()
+                .map(|line_no| format!("{}:{line_no}", self.path.display()))
+        }
+
+        fn read_error(&self, what: impl fmt::Display, e: io::Error) -> Box<EvalAltResult> {
+            Box::new(EvalAltResult::ErrorRuntime(
+                format!("Failed to read {what} of {:?}: {e}", self.path).into(),
+                Position::NONE,
+            ))
+        }
+
+        fn seek(&mut self, offset: u64) -> RhaiRes<()> {
+            self.peeked = None;
+            self.file
+                .seek(SeekFrom::Start(offset))
+                .map(|_| ())
+                .map_err(|e| self.read_error(format_args!("offset {offset}"), e))
+        }
+
+        /// Run `f` as if line `line_no` started at byte `offset`, then continue reading
+        /// where we were before
+        fn read_at<T>(
+            &mut self,
+            offset: u64,
+            line_no: usize,
+            f: impl FnOnce(&mut Self) -> RhaiRes<T>,
+        ) -> RhaiRes<T> {
+            let orig = self.pos;
+            let orig_processing = *self.processing.borrow();
+            self.seek(offset)?;
+            self.pos = ReadPos { line_no, offset };
+            let ret = f(self);
+            self.seek(orig.offset)?;
+            self.pos = orig;
+            *self.processing.borrow_mut() = orig_processing;
+            ret
+        }
+
+        fn line_starts(&mut self) -> RhaiRes<&[u64]> {
+            if self.line_starts.is_none() {
+                let starts = self.read_at(0, 0, |data| {
+                    let mut starts = Vec::new();
+                    let mut offset = 0;
+                    let mut buf = Vec::new();
+                    loop {
+                        buf.clear();
+                        let res = data.file.read_until(b'\n', &mut buf);
+                        if !data.count_line(res)? {
+                            break;
+                        }
+                        starts.push(offset);
+                        offset += buf.len() as u64;
+                    }
+                    Ok(starts)
+                })?;
+                self.line_starts = Some(starts);
+            }
+            Ok(self.line_starts.as_deref().unwrap())
+        }
+
+        /// Read the next line with its line break into `buf`, unless `peek_line` already did
+        fn read_raw_line(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+            match self.peeked.take() {
+                Some(line) => {
+                    *buf = line;
+                    Ok(buf.len())
+                }
+                None => self.file.read_until(b'\n', buf),
+            }
+        }
+
+        pub fn read_line(&mut self) -> RhaiRes<Option<ImmutableString>> {
+            let mut buf = Vec::new();
+            let res = self.read_raw_line(&mut buf).and_then(|len| {
+                std::str::from_utf8(&buf)
+                    .map(|_| len)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            });
+            if !self.count_line(res)? {
+                return Ok(None);
+            }
+            let mut ret = String::from_utf8(buf).unwrap();
             while matches!(ret.as_bytes().last(), Some(b'\r' | b'\n')) {
                 ret.pop();
             }
-            (actual > 0).then_some(ret.into())
+            Ok(Some(ret.into()))
+        }
+
+        pub fn read_blob(&mut self) -> RhaiRes<Option<Blob>> {
+            let mut ret = Vec::new();
+            let res = self.read_raw_line(&mut ret);
+            if !self.count_line(res)? {
+                return Ok(None);
+            }
+            while matches!(ret.last(), Some(b'\r' | b'\n')) {
+                ret.pop();
+            }
+            Ok(Some(ret))
+        }
+
+        /// Read the lines up to the next blank line, skipping any leading blank lines so that
+        /// repeated or trailing blank lines never produce an empty paragraph.
+        fn read_paragraph<T: rhai::Variant + Clone>(
+            &mut self,
+            read: impl Fn(&mut Self) -> RhaiRes<Option<T>>,
+            is_blank: impl Fn(&T) -> bool,
+        ) -> RhaiRes<Option<Array>> {
+            let mut ret = Array::new();
+            while let Some(line) = read(self)? {
+                if !is_blank(&line) {
+                    ret.push(Dynamic::from(line));
+                } else if !ret.is_empty() {
+                    break;
+                }
+            }
+            Ok((!ret.is_empty()).then_some(ret))
+        }
+    }
+
+    /// Value for each byte of a grid, `None` for the bytes that are not allowed
+    struct CharMap([Option<u32>; 256]);
+
+    impl CharMap {
+        fn byte(ctx: &NativeCallContext, c: char) -> RhaiRes<u8> {
+            u8::try_from(c)
+                .ok()
+                .filter(u8::is_ascii)
+                .ok_or_else(|| runtime_error(ctx, format!("Only ASCII can be mapped, got {c:?}")))
+        }
+
+        fn from_str(ctx: &NativeCallContext, char_map: &str) -> RhaiRes<Self> {
+            let mut ret = [None; 256];
+            for (value, c) in char_map.chars().enumerate() {
+                ret[usize::from(Self::byte(ctx, c)?)] = Some(try_from(ctx, value)?);
+            }
+            Ok(Self(ret))
+        }
+
+        fn from_map(ctx: &NativeCallContext, char_map: &Map) -> RhaiRes<Self> {
+            let mut ret = [None; 256];
+            for (key, value) in char_map {
+                let mut chars = key.chars();
+                let c = match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => {
+                        return Err(mismatching_data_type(
+                            ctx,
+                            "single character key",
+                            key.as_str(),
+                        ))
+                    }
+                };
+                let value = value
+                    .as_int()
+                    .map_err(|e| mismatching_data_type(ctx, "integer", e))?;
+                ret[usize::from(Self::byte(ctx, c)?)] = Some(try_from(ctx, value)?);
+            }
+            Ok(Self(ret))
         }
     }
 
-    pub fn next_line(data: &mut SharedAocData) -> Dynamic {
+    impl AocData {
+        /// Read lines into an image until the end of the data or a blank line. Returns the
+        /// positions of each of the `markers` characters together with the image.
+        fn read_grid(
+            &mut self,
+            ctx: &NativeCallContext,
+            char_map: Option<&CharMap>,
+            markers: &str,
+        ) -> RhaiRes<(SharedDynImg, Map)> {
+            let mut marker_pos = HashMap::new();
+            for c in markers.chars() {
+                marker_pos.insert(CharMap::byte(ctx, c)?, (c, Array::new()));
+            }
+
+            let mut width = None;
+            let mut pixels = Vec::new();
+            let mut y = 0;
+            while let Some(row) = self.read_blob().map_err(|e| at_call(ctx, e))? {
+                if row.is_empty() {
+                    break;
+                }
+                let line_no = self.line_base + self.pos.line_no;
+                match width {
+                    None => width = Some(row.len()),
+                    Some(width) if width != row.len() => {
+                        return Err(runtime_error(
+                            ctx,
+                            format!(
+                                "Line {line_no} of {:?} has {} characters, expected {width}",
+                                self.path,
+                                row.len()
+                            ),
+                        ))
+                    }
+                    Some(_) => {}
+                }
+
+                for (x, b) in row.into_iter().enumerate() {
+                    if let Some((_, positions)) = marker_pos.get_mut(&b) {
+                        let pos: (INT, INT) = (try_from(ctx, x)?, try_from(ctx, y)?);
+                        positions.push(Dynamic::from(pos));
+                    }
+                    pixels.push(match char_map {
+                        Some(CharMap(char_map)) => char_map[usize::from(b)].ok_or_else(|| {
+                            runtime_error(
+                                ctx,
+                                format!(
+                                    "Unexpected {:?} at line {line_no}, column {} of {:?}",
+                                    char::from(b),
+                                    x + 1,
+                                    self.path
+                                ),
+                            )
+                        })?,
+                        None => u32::from(b),
+                    });
+                }
+                y += 1;
+            }
+
+            let width = width.ok_or_else(|| runtime_error(ctx, "No rows to load into a grid"))?;
+            let markers = marker_pos
+                .into_values()
+                .map(|(c, positions)| (c.to_string().into(), positions.into()))
+                .collect();
+            Ok((DynamicImage::new_shared(width, pixels), markers))
+        }
+    }
+
+    /// Load the lines up to the next blank line as an image of the raw bytes
+    #[rhai_fn(return_raw)]
+    pub fn grid(ctx: NativeCallContext, data: SharedAocData) -> RhaiRes<SharedDynImg> {
+        Ok(data.borrow_mut().read_grid(&ctx, None, "")?.0)
+    }
+
+    /// Load a grid where each character gets the value of its index in `char_map`
+    #[rhai_fn(name = "grid", return_raw)]
+    pub fn grid_str(
+        ctx: NativeCallContext,
+        data: SharedAocData,
+        char_map: &str,
+    ) -> RhaiRes<SharedDynImg> {
+        let char_map = CharMap::from_str(&ctx, char_map)?;
+        Ok(data.borrow_mut().read_grid(&ctx, Some(&char_map), "")?.0)
+    }
+
+    /// Load a grid where each character gets the value it has in `char_map`
+    #[rhai_fn(name = "grid", return_raw)]
+    pub fn grid_map(
+        ctx: NativeCallContext,
+        data: SharedAocData,
+        char_map: Map,
+    ) -> RhaiRes<SharedDynImg> {
+        let char_map = CharMap::from_map(&ctx, &char_map)?;
+        Ok(data.borrow_mut().read_grid(&ctx, Some(&char_map), "")?.0)
+    }
+
+    /// Like `grid(char_map)`, but returns a map with the image as `grid` and an array of
+    /// the positions for each of the `markers` characters
+    #[rhai_fn(name = "grid", return_raw)]
+    pub fn grid_str_markers(
+        ctx: NativeCallContext,
+        data: SharedAocData,
+        char_map: &str,
+        markers: &str,
+    ) -> RhaiRes<Map> {
+        let char_map = CharMap::from_str(&ctx, char_map)?;
+        let (img, mut ret) = data
+            .borrow_mut()
+            .read_grid(&ctx, Some(&char_map), markers)?;
+        ret.insert("grid".into(), Dynamic::from(img));
+        Ok(ret)
+    }
+
+    #[rhai_fn(name = "grid", return_raw)]
+    pub fn grid_map_markers(
+        ctx: NativeCallContext,
+        data: SharedAocData,
+        char_map: Map,
+        markers: &str,
+    ) -> RhaiRes<Map> {
+        let char_map = CharMap::from_map(&ctx, &char_map)?;
+        let (img, mut ret) = data
+            .borrow_mut()
+            .read_grid(&ctx, Some(&char_map), markers)?;
+        ret.insert("grid".into(), Dynamic::from(img));
+        Ok(ret)
+    }
+
+    /// All the integers in the whole data, without changing what is read next. See `ints`
+    /// for strings.
+    #[rhai_fn(return_raw)]
+    pub fn ints(ctx: NativeCallContext, data: SharedAocData) -> RhaiRes<SharedIntArray> {
         data.borrow_mut()
+            .read_at(0, 0, |data| {
+                let mut values = Vec::new();
+                while let Some(line) = data.read_line()? {
+                    let line_values = IntArray::parse_all(&line).map_err(|e| {
+                        let location = data.location().unwrap_or_default();
+                        Box::new(EvalAltResult::ErrorArithmetic(
+                            format!("{e} on {location}"),
+                            Position::NONE,
+                        ))
+                    })?;
+                    values.extend(line_values);
+                }
+                Ok(IntArray::new_shared(values))
+            })
+            .map_err(|e| at_call(&ctx, e))
+    }
+
+    /// The next line or `()` at the end of the data
+    #[rhai_fn(return_raw)]
+    pub fn next_line(ctx: NativeCallContext, data: &mut SharedAocData) -> RhaiRes<Dynamic> {
+        Ok(data
+            .borrow_mut()
             .read_line()
+            .map_err(|e| at_call(&ctx, e))?
             .map(Dynamic::from)
-            .unwrap_or(Dynamic::UNIT)
+            .unwrap_or(Dynamic::UNIT))
+    }
+
+    /// Number in the file of the line read last. Before the first line this is the number
+    /// of the line in front of the data, 0 for a whole file.
+    #[rhai_fn(get = "line_no", pure, return_raw)]
+    pub fn get_line_no(ctx: NativeCallContext, data: &mut SharedAocData) -> RhaiRes<INT> {
+        let data = data.borrow();
+        try_from(&ctx, data.line_base + data.pos.line_no)
+    }
+
+    /// Byte offset of the next line from the start of this data. Sections and cases have
+    /// their own text with `\n` line breaks, so for them it is not an offset in the file.
+    #[rhai_fn(get = "offset", pure, return_raw)]
+    pub fn get_offset(ctx: NativeCallContext, data: &mut SharedAocData) -> RhaiRes<INT> {
+        try_from(&ctx, data.borrow().pos.offset)
+    }
+
+    /// Start reading from the first line again
+    #[rhai_fn(return_raw)]
+    pub fn rewind(ctx: NativeCallContext, data: &mut SharedAocData) -> RhaiRes<()> {
+        let mut data = data.borrow_mut();
+        data.seek(0).map_err(|e| at_call(&ctx, e))?;
+        data.pos = ReadPos::default();
+        *data.processing.borrow_mut() = None;
+        Ok(())
+    }
+
+    /// The next line without consuming it, `()` at the end of the data
+    #[rhai_fn(return_raw)]
+    pub fn peek_line(ctx: NativeCallContext, data: &mut SharedAocData) -> RhaiRes<Dynamic> {
+        let mut data = data.borrow_mut();
+        if data.peeked.is_none() {
+            let mut buf = Vec::new();
+            if let Err(e) = data.file.read_until(b'\n', &mut buf) {
+                let line_no = data.line_base + data.pos.line_no + 1;
+                return Err(at_call(&ctx, data.read_error(format!("line {line_no}"), e)));
+            }
+            data.peeked = Some(buf);
+        }
+        let mut line = data.peeked.as_deref().unwrap();
+        if line.is_empty() {
+            return Ok(Dynamic::UNIT);
+        }
+        while let [rest @ .., b'\r' | b'\n'] = line {
+            line = rest;
+        }
+        std::str::from_utf8(line)
+            .map(|line| ImmutableString::from(line).into())
+            .map_err(|e| runtime_error(&ctx, format!("Line is not UTF-8: {e}")))
+    }
+
+    /// The line with number `line_no`, without changing what is read next. Lines are
+    /// numbered like `line_no`, so `data.line(data.line_no)` is the line read last.
+    #[rhai_fn(return_raw)]
+    pub fn line(
+        ctx: NativeCallContext,
+        data: &mut SharedAocData,
+        line_no: INT,
+    ) -> RhaiRes<ImmutableString> {
+        let mut data = data.borrow_mut();
+        let line_base = data.line_base;
+        let starts = data.line_starts().map_err(|e| at_call(&ctx, e))?;
+        let (i, offset) = usize::try_from(line_no)
+            .ok()
+            .and_then(|n| n.checked_sub(line_base + 1))
+            .and_then(|i| Some((i, *starts.get(i)?)))
+            .ok_or_else(|| index_not_found(&ctx, line_no))?;
+        data.read_at(offset, i, AocData::read_line)
+            .map_err(|e| at_call(&ctx, e))?
+            .ok_or_else(|| index_not_found(&ctx, line_no))
+    }
+
+    /// Number of lines in the whole data
+    #[rhai_fn(return_raw)]
+    pub fn line_count(ctx: NativeCallContext, data: &mut SharedAocData) -> RhaiRes<INT> {
+        let mut data = data.borrow_mut();
+        let count = data.line_starts().map_err(|e| at_call(&ctx, e))?.len();
+        try_from(&ctx, count)
+    }
+
+    /// The whole data as one string, without changing what is read next
+    #[rhai_fn(return_raw)]
+    pub fn text(ctx: NativeCallContext, data: &mut SharedAocData) -> RhaiRes<ImmutableString> {
+        data.borrow_mut()
+            .read_at(0, 0, |data| {
+                let mut text = String::new();
+                data.file
+                    .read_to_string(&mut text)
+                    .map_err(|e| data.read_error("the text", e))?;
+                Ok(text.into())
+            })
+            .map_err(|e| at_call(&ctx, e))
     }
 
     pub fn lines(data: SharedAocData) -> DynIterator<ImmutableString> {
-        DynIterator::new(std::iter::from_fn(move || data.borrow_mut().read_line()))
+        DynIterator::new_fallible(std::iter::from_fn(move || {
+            data.borrow_mut().read_line().transpose()
+        }))
     }
 
-    pub fn blobs(data: SharedAocData) -> DynIterator<Blob> {
-        DynIterator::new(std::iter::from_fn(move || {
+    /// Like `lines`, but each line is a `Line` that knows its number and the data file
+    pub fn numbered_lines(data: SharedAocData) -> DynIterator<Line> {
+        DynIterator::new_fallible(std::iter::from_fn(move || {
             let mut data = data.borrow_mut();
-            let mut ret = Vec::new();
-            let actual = data
-                .file
-                .read_until(b'\n', &mut ret)
-                .wrap_err_with(|| format!("Failed to read the next line from {:?}", data.path))
-                .unwrap(); // ToDo: Fallible iterator support?
-            while matches!(ret.last(), Some(b'\r' | b'\n')) {
-                ret.pop();
-            }
-            (actual > 0).then_some(ret)
+            let text = data.read_line().transpose()?;
+            let line_no = (data.line_base + data.pos.line_no).try_into().unwrap();
+            let file = data.path.display().to_string().into();
+            Some(text.map(|text| Line::new(text, line_no, file)))
+        }))
+    }
+
+    pub fn blobs(data: SharedAocData) -> DynIterator<Blob> {
+        DynIterator::new_fallible(std::iter::from_fn(move || {
+            data.borrow_mut().read_blob().transpose()
+        }))
+    }
+
+    /// The lines up to the next blank line as a reader of their own, `()` at the end of
+    /// the data. Line numbers still count from the start of the file.
+    #[rhai_fn(return_raw)]
+    pub fn next_section(ctx: NativeCallContext, data: &mut SharedAocData) -> RhaiRes<Dynamic> {
+        Ok(data
+            .borrow_mut()
+            .read_section()
+            .map_err(|e| at_call(&ctx, e))?
+            .map(Dynamic::from)
+            .unwrap_or(Dynamic::UNIT))
+    }
+
+    /// Iterate the blank line separated sections as readers, see `next_section`
+    pub fn sections(data: SharedAocData) -> DynIterator<SharedAocData> {
+        DynIterator::new_fallible(std::iter::from_fn(move || {
+            data.borrow_mut().read_section().transpose()
+        }))
+    }
+
+    /// Iterate the blank line separated paragraphs as arrays of lines
+    pub fn paragraphs(data: SharedAocData) -> DynIterator<Array> {
+        DynIterator::new_fallible(std::iter::from_fn(move || {
+            data.borrow_mut()
+                .read_paragraph(AocData::read_line, |line| line.is_empty())
+                .transpose()
+        }))
+    }
+
+    /// Iterate the blank line separated paragraphs as arrays of blobs
+    pub fn paragraph_blobs(data: SharedAocData) -> DynIterator<Array> {
+        DynIterator::new_fallible(std::iter::from_fn(move || {
+            data.borrow_mut()
+                .read_paragraph(AocData::read_blob, |line| line.is_empty())
+                .transpose()
         }))
     }
 }
@@ -40,4 +40,50 @@ mod blob_extras {
             }
         }
     }
+
+    /// Every run of `len` consecutive bytes
+    #[rhai_fn(name = "windows", return_raw)]
+    pub fn blob_windows(ctx: NativeCallContext, a: Blob, len: INT) -> RhaiRes<DynIterator<Blob>> {
+        Ok(slices::map_windows(
+            a,
+            slices::slice_len(&ctx, len)?,
+            <[_]>::to_vec,
+        ))
+    }
+
+    /// `len` bytes at a time, the last chunk may be shorter
+    #[rhai_fn(name = "chunks", return_raw)]
+    pub fn blob_chunks(ctx: NativeCallContext, a: Blob, len: INT) -> RhaiRes<DynIterator<Blob>> {
+        Ok(slices::map_chunks(
+            a,
+            slices::slice_len(&ctx, len)?,
+            <[_]>::to_vec,
+        ))
+    }
+
+    /// The end index of the first `len` consecutive bytes that are all different, `()` if
+    /// there are none
+    #[rhai_fn(pure, return_raw)]
+    pub fn first_unique_window(ctx: NativeCallContext, a: &mut Blob, len: INT) -> RhaiRes<Dynamic> {
+        let len = slices::slice_len(&ctx, len)?;
+        let mut counts = [0usize; 256];
+        let mut dups = 0;
+        for (i, &b) in a.iter().enumerate() {
+            counts[usize::from(b)] += 1;
+            if counts[usize::from(b)] == 2 {
+                dups += 1;
+            }
+            if i >= len {
+                let old = usize::from(a[i - len]);
+                counts[old] -= 1;
+                if counts[old] == 1 {
+                    dups -= 1;
+                }
+            }
+            if i + 1 >= len && dups == 0 {
+                return Ok(Dynamic::from(try_from::<_, INT, _>(&ctx, i + 1)?));
+            }
+        }
+        Ok(Dynamic::UNIT)
+    }
 }
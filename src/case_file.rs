@@ -0,0 +1,96 @@
+use crate::*;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+};
+
+const HEADER: &str = ">>> ";
+
+pub struct Case {
+    pub name: String,
+    pub answers: [Option<String>; 2],
+    pub text: String,
+    /// JSON object of parameters that override those of the data file for this case
+    pub params: Option<String>,
+    /// Number of lines in the file in front of the data of the case
+    pub line_base: usize,
+}
+
+/// Check the first line of the file for a case header without reading the rest of it
+pub fn is_case_file(path: &Path) -> Result<bool> {
+    let mut first_line = String::new();
+    File::open(path)
+        .map(BufReader::new)
+        .and_then(|mut file| file.read_line(&mut first_line))
+        .wrap_err_with(|| format!("Failed to read the first line of {path:?}"))?;
+    Ok(first_line
+        .strip_prefix(HEADER)
+        .is_some_and(|v| v.starts_with("case ")))
+}
+
+/// Load an example file holding several named cases, each with its own expected answers
+/// and optionally parameters that override those of `params.json`:
+///
+/// ```text
+/// >>> case first
+/// >>> part1: 7
+/// >>> part2: 19
+/// mjqjpqmgbljsphdztnvjfqwrcgsmlb
+/// >>> case second
+/// >>> params: {"row": 10}
+/// ...
+/// ```
+pub fn load(path: &Path) -> Result<Vec<Case>> {
+    let text = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("Failed to read cases from {path:?}"))?;
+    let mut ret: Vec<Case> = Vec::new();
+
+    for (ln, line) in text.lines().enumerate() {
+        let Some(header) = line.strip_prefix(HEADER) else {
+            let case = ret
+                .last_mut()
+                .ok_or_else(|| eyre!("{path:?}:{}: Data before the first case", ln + 1))?;
+            if case.text.is_empty() {
+                case.line_base = ln;
+            }
+            case.text.push_str(line);
+            case.text.push('\n');
+            continue;
+        };
+
+        if let Some(name) = header.strip_prefix("case ") {
+            ret.push(Case {
+                name: name.trim().into(),
+                answers: [None, None],
+                text: String::new(),
+                params: None,
+                line_base: ln + 1,
+            });
+            continue;
+        }
+
+        let (key, value) = header
+            .split_once(':')
+            .ok_or_else(|| eyre!("{path:?}:{}: Invalid case header {line:?}", ln + 1))?;
+        let case = ret
+            .last_mut()
+            .ok_or_else(|| eyre!("{path:?}:{}: Header before the first case", ln + 1))?;
+        ensure!(
+            case.text.is_empty(),
+            "{path:?}:{}: Headers must come before the data of the case",
+            ln + 1
+        );
+        let part = match key.trim() {
+            "part1" => 0,
+            "part2" => 1,
+            "params" => {
+                case.params = Some(value.trim().into());
+                continue;
+            }
+            _ => bail!("{path:?}:{}: Unknown case header {key:?}", ln + 1),
+        };
+        case.answers[part] = Some(value.trim().into());
+    }
+
+    Ok(ret)
+}
@@ -0,0 +1,257 @@
+use crate::*;
+use rhai::{ASTNode, Engine, Expr, FnCallExpr, OptimizationLevel, Position, Stmt, AST};
+use std::{collections::HashMap, fs};
+
+/// Functions that are handled by the engine itself and not registered in any module
+const KEYWORD_FNS: &[&str] = &[
+    "print",
+    "debug",
+    "type_of",
+    "eval",
+    "Fn",
+    "call",
+    "curry",
+    "is_shared",
+    "is_def_var",
+    "is_def_fn",
+];
+
+type FnSet = HashSet<(String, usize)>;
+
+fn native_fns(engine: &Engine) -> Result<FnSet> {
+    let meta: serde_json::Value = serde_json::from_str(&engine.gen_fn_metadata_to_json(true)?)?;
+    let mut ret = FnSet::new();
+    for f in meta["functions"].as_array().into_iter().flatten() {
+        if let (Some(name), Some(num_params)) = (f["name"].as_str(), f["numParams"].as_u64()) {
+            ret.insert((name.into(), num_params as usize));
+        }
+    }
+    Ok(ret)
+}
+
+fn script_fns(ast: &AST) -> FnSet {
+    ast.iter_fn_def()
+        .map(|f| (f.name.to_string(), f.params.len()))
+        .collect()
+}
+
+struct Checker<'a> {
+    engine: &'a Engine,
+    native: &'a FnSet,
+    module_dir: &'a Path,
+    problems: Vec<String>,
+}
+
+impl Checker<'_> {
+    fn report(&mut self, path: &Path, pos: Position, msg: impl std::fmt::Display) {
+        self.problems
+            .push(format!("{}: {msg}", fmt_location(path, pos)));
+    }
+
+    fn check_file(&mut self, path: &Path) {
+        let ast = match self.engine.compile_file(path.into()) {
+            Ok(ast) => ast,
+            Err(mut e) => {
+                let pos = e.take_position();
+                self.report(path, pos, e);
+                return;
+            }
+        };
+
+        let defined = script_fns(&ast);
+        let mut imports = HashMap::new();
+        let mut dynamic = HashSet::new();
+        let mut calls = Vec::new();
+
+        ast.walk(&mut |nodes| {
+            match nodes.last() {
+                Some(ASTNode::Stmt(Stmt::Import(x, pos))) => {
+                    if let (Expr::StringConstant(name, _), alias) = &**x {
+                        imports.insert(alias.name.clone(), (name.clone(), *pos));
+                    }
+                }
+                Some(ASTNode::Stmt(Stmt::FnCall(x, pos)))
+                | Some(ASTNode::Expr(Expr::FnCall(x, pos))) => {
+                    calls.push((x.clone(), *pos, false));
+                }
+                Some(ASTNode::Expr(Expr::MethodCall(x, pos))) => {
+                    calls.push((x.clone(), *pos, true))
+                }
+                // Closures stored in object maps are called like methods
+                Some(ASTNode::Expr(Expr::Property(x, _))) => {
+                    dynamic.insert(x.2.clone());
+                }
+                Some(ASTNode::Expr(Expr::Map(x, _))) => {
+                    dynamic.extend(x.0.iter().map(|(ident, _)| ident.name.clone()));
+                }
+                _ => {}
+            }
+            true
+        });
+
+        let mut modules = HashMap::new();
+        for (alias, (name, pos)) in imports {
+            let module_path = self.module_dir.join(name.as_str()).with_extension("rhai");
+            match self.engine.compile_file(module_path.clone()) {
+                Ok(ast) => {
+                    modules.insert(alias, script_fns(&ast));
+                }
+                Err(e) => self.report(path, pos, format!("Failed to import {module_path:?}: {e}")),
+            }
+        }
+
+        for (call, pos, is_method) in calls {
+            let FnCallExpr { name, args, .. } = &*call;
+            if call.op_token.is_some() || !name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            {
+                continue;
+            }
+            let num_args = args.len() + usize::from(is_method);
+
+            let known: Vec<&FnSet> = if call.is_qualified() {
+                let root = call.namespace.root();
+                match modules.get(root) {
+                    Some(fns) => vec![fns],
+                    None if root == "global" => vec![&defined],
+                    None => {
+                        self.report(
+                            path,
+                            pos,
+                            format!("Unknown module `{root}` in call to `{name}`"),
+                        );
+                        continue;
+                    }
+                }
+            } else if KEYWORD_FNS.contains(&name.as_str()) || (is_method && dynamic.contains(name))
+            {
+                continue;
+            } else {
+                vec![&defined, self.native]
+            };
+
+            if known
+                .iter()
+                .any(|fns| fns.contains(&(name.to_string(), num_args)))
+            {
+                continue;
+            }
+
+            let mut arities: Vec<_> = known
+                .iter()
+                .flat_map(|fns| fns.iter())
+                .filter(|(n, _)| n == name.as_str())
+                .map(|(_, num_params)| *num_params)
+                .collect();
+            if arities.is_empty() {
+                self.report(path, pos, format!("Call to unknown function `{name}`"));
+            } else {
+                arities.sort_unstable();
+                arities.dedup();
+                self.report(
+                    path,
+                    pos,
+                    format!(
+                        "`{name}` called with {num_args} argument(s), but it takes {arities:?}"
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn find_scripts(dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).wrap_err_with(|| format!("Failed to list {dir:?}"))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_scripts(&path, out)?;
+        } else if path.extension().is_some_and(|ext| ext == "rhai") {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Names of the modules a script imports with a constant path, like `lib/parsing`
+fn script_imports(ast: &AST) -> Vec<ImmutableString> {
+    let mut ret = Vec::new();
+    ast.walk(&mut |nodes| {
+        if let Some(ASTNode::Stmt(Stmt::Import(x, _))) = nodes.last() {
+            if let (Expr::StringConstant(name, _), _) = &**x {
+                ret.push(name.clone());
+            }
+        }
+        true
+    });
+    ret
+}
+
+/// The days whose script imports `module`, directly or through other modules under
+/// `module_dir`. These are the days to run again after the module changed.
+pub fn importers(engine: &Engine, module_dir: &Path, module: &str) -> Result<Vec<u8>> {
+    let mut scripts = Vec::new();
+    find_scripts(module_dir, &mut scripts)?;
+
+    // Module name, as used in `import`, to the names of the scripts importing it
+    let mut imported_by: HashMap<String, Vec<String>> = HashMap::new();
+    for path in &scripts {
+        let ast = engine
+            .compile_file(path.clone())
+            .map_err(|e| eyre!("{e}"))
+            .wrap_err_with(|| format!("Failed to compile {path:?}"))?;
+        let name = path
+            .strip_prefix(module_dir)?
+            .with_extension("")
+            .to_string_lossy()
+            .replace('\\', "/");
+        for import in script_imports(&ast) {
+            imported_by
+                .entry(import.to_string())
+                .or_default()
+                .push(name.clone());
+        }
+    }
+
+    let mut days = Vec::new();
+    let mut seen = HashSet::new();
+    let mut todo = vec![module.to_string()];
+    while let Some(name) = todo.pop() {
+        for importer in imported_by.get(&name).into_iter().flatten() {
+            if !seen.insert(importer.clone()) {
+                continue;
+            }
+            let day = importer
+                .strip_suffix("/script")
+                .and_then(|dir| dir.strip_prefix("day-"))
+                .and_then(|day| day.parse().ok());
+            match day {
+                Some(day) => days.push(day),
+                None => todo.push(importer.clone()),
+            }
+        }
+    }
+    days.sort_unstable();
+    Ok(days)
+}
+
+/// Compile every script under `module_dir` without running it. Returns the syntax errors
+/// and calls to functions that are not defined anywhere, each with its `file:line:col`.
+pub fn check_scripts(mut engine: Engine, module_dir: &Path) -> Result<Vec<String>> {
+    // Keep the AST as written, the optimizer may fold away calls
+    engine.set_optimization_level(OptimizationLevel::None);
+
+    let native = native_fns(&engine)?;
+    let mut scripts = Vec::new();
+    find_scripts(module_dir, &mut scripts)?;
+    scripts.sort();
+
+    let mut checker = Checker {
+        engine: &engine,
+        native: &native,
+        module_dir,
+        problems: Vec::new(),
+    };
+    for path in &scripts {
+        checker.check_file(path);
+    }
+    Ok(checker.problems)
+}
@@ -0,0 +1,255 @@
+use crate::{line::Line, *};
+
+pub use self::collect::*;
+
+type Items<'a> = &'a mut dyn Iterator<Item = RhaiRes<Dynamic>>;
+
+fn new_set<T>(values: HashSet<T>) -> SharedSet<T> {
+    Shared::new(Locked::new(values))
+}
+
+pub fn collect_ints(ctx: &NativeCallContext, items: Items) -> RhaiRes<SharedIntArray> {
+    let values = items
+        .map(|v| {
+            v?.as_int()
+                .map_err(|e| mismatching_data_type(ctx, "integer", e))
+        })
+        .collect::<RhaiRes<_>>()?;
+    Ok(IntArray::new_shared(values))
+}
+
+/// The element type of the set is taken from the first item, an empty set holds integers
+pub fn collect_set(ctx: &NativeCallContext, items: Items) -> RhaiRes<Dynamic> {
+    let mut items = items.peekable();
+    let first = match items.peek() {
+        Some(Ok(first)) => first.clone(),
+        Some(Err(_)) => return Err(items.next().unwrap().unwrap_err()),
+        None => return Ok(Dynamic::from(new_set(HashSet::<INT>::new()))),
+    };
+    let expected = "integers, strings or (INT, INT) tuples";
+    if first.is::<INT>() {
+        let values = items
+            .map(|v| {
+                v?.as_int()
+                    .map_err(|e| mismatching_data_type(ctx, expected, e))
+            })
+            .collect::<RhaiRes<_>>()?;
+        Ok(Dynamic::from(new_set::<INT>(values)))
+    } else if first.is::<ImmutableString>() {
+        let values = items
+            .map(|v| {
+                v?.into_immutable_string()
+                    .map_err(|e| mismatching_data_type(ctx, expected, e))
+            })
+            .collect::<RhaiRes<_>>()?;
+        Ok(Dynamic::from(new_set::<ImmutableString>(values)))
+    } else if first.is::<(INT, INT)>() {
+        let values = items
+            .map(|v| {
+                let v = v?;
+                v.clone()
+                    .try_cast::<(INT, INT)>()
+                    .ok_or_else(|| mismatching_data_type(ctx, expected, v.type_name()))
+            })
+            .collect::<RhaiRes<_>>()?;
+        Ok(Dynamic::from(new_set::<(INT, INT)>(values)))
+    } else {
+        Err(mismatching_data_type(ctx, expected, first.type_name()))
+    }
+}
+
+/// The map key for `v`, `None` for types without one. Strings and chars are their own key,
+/// other values are keyed by their debug form, which tells apart what their string form
+/// does not. Only nested strings and chars are quoted, so at the top level `1` and `"1"`,
+/// or `'a'` and `"a"`, share a key.
+fn map_key(v: &Dynamic) -> Option<String> {
+    if let Some(s) = v.read_lock::<ImmutableString>() {
+        return Some(s.to_string());
+    }
+    if let Some(c) = v.read_lock::<char>() {
+        return Some(c.to_string());
+    }
+    if let Some(line) = v.read_lock::<Line>() {
+        return Some(line.text().to_string());
+    }
+    nested_key(v)
+}
+
+/// The key of a value inside a key, strings and chars are quoted here
+fn nested_key(v: &Dynamic) -> Option<String> {
+    macro_rules! debug_form {
+        ($($t:ty),*) => {$(
+            if let Some(v) = v.read_lock::<$t>() {
+                return Some(format!("{:?}", *v));
+            }
+        )*};
+    }
+    debug_form!((), INT, bool, char, ImmutableString, (INT, INT));
+    if let Some(a) = v.read_lock::<SharedIntArray>() {
+        return Some(format!("{:?}", a.borrow().values()));
+    }
+    if let Some(a) = v.read_lock::<Array>() {
+        let keys = a.iter().map(nested_key).collect::<Option<Vec<_>>>()?;
+        return Some(format!("[{}]", keys.join(", ")));
+    }
+    None
+}
+
+fn key_of(ctx: &NativeCallContext, v: &Dynamic) -> RhaiRes<rhai::Identifier> {
+    map_key(v).map(Into::into).ok_or_else(|| {
+        mismatching_data_type(
+            ctx,
+            "a string, integer, char, bool, (INT, INT), array or IntArray key",
+            v.type_name(),
+        )
+    })
+}
+
+fn key_fn_of(ctx: &NativeCallContext, f: &rhai::FnPtr, v: Dynamic) -> RhaiRes<rhai::Identifier> {
+    key_of(ctx, &f.call_raw(ctx, None, [v])?)
+}
+
+pub fn collect_map(
+    ctx: &NativeCallContext,
+    items: Items,
+    key_fn: rhai::FnPtr,
+    value_fn: rhai::FnPtr,
+) -> RhaiRes<rhai::Map> {
+    let mut ret = rhai::Map::new();
+    for v in items {
+        let v = v?;
+        let key = key_fn_of(ctx, &key_fn, v.clone())?;
+        ret.insert(key, value_fn.call_raw(ctx, None, [v])?);
+    }
+    Ok(ret)
+}
+
+pub fn counts(ctx: &NativeCallContext, items: Items) -> RhaiRes<rhai::Map> {
+    let mut ret = rhai::Map::new();
+    for v in items {
+        let count = ret
+            .entry(key_of(ctx, &v?)?)
+            .or_insert(Dynamic::from(0 as INT));
+        *count = Dynamic::from(count.as_int().unwrap() + 1);
+    }
+    Ok(ret)
+}
+
+pub fn group_by(ctx: &NativeCallContext, items: Items, key_fn: rhai::FnPtr) -> RhaiRes<rhai::Map> {
+    let mut groups: HashMap<rhai::Identifier, Array> = HashMap::new();
+    for v in items {
+        let v = v?;
+        groups
+            .entry(key_fn_of(ctx, &key_fn, v.clone())?)
+            .or_default()
+            .push(v);
+    }
+    Ok(groups.into_iter().map(|(k, v)| (k, v.into())).collect())
+}
+
+#[export_module]
+mod collect {
+    use rhai::{FnPtr, Map};
+
+    /// The elements, which must all be integers, as an `IntArray`
+    #[rhai_fn(name = "collect_ints", return_raw)]
+    pub fn array_collect_ints(ctx: NativeCallContext, a: Array) -> RhaiRes<SharedIntArray> {
+        super::collect_ints(&ctx, &mut a.into_iter().map(Ok))
+    }
+
+    /// A set of the elements, which must be integers, strings or `(INT, INT)` tuples
+    #[rhai_fn(name = "collect_set", return_raw)]
+    pub fn array_collect_set(ctx: NativeCallContext, a: Array) -> RhaiRes<Dynamic> {
+        super::collect_set(&ctx, &mut a.into_iter().map(Ok))
+    }
+
+    /// Map `key_fn(v)` to `value_fn(v)` for each element, later elements win. The keys are
+    /// made like those of `counts`.
+    #[rhai_fn(name = "collect_map", return_raw)]
+    pub fn array_collect_map(
+        ctx: NativeCallContext,
+        a: Array,
+        key_fn: FnPtr,
+        value_fn: FnPtr,
+    ) -> RhaiRes<Map> {
+        super::collect_map(&ctx, &mut a.into_iter().map(Ok), key_fn, value_fn)
+    }
+
+    /// How often each element occurs. Strings and chars are their own key, other values
+    /// are keyed by their debug form. Elements of different types can share a key this
+    /// way, `[1, "1"].counts()` is `#{"1": 2}`.
+    #[rhai_fn(name = "counts", return_raw)]
+    pub fn array_counts(ctx: NativeCallContext, a: Array) -> RhaiRes<Map> {
+        super::counts(&ctx, &mut a.into_iter().map(Ok))
+    }
+
+    /// The elements grouped into arrays by `key_fn(v)`, keyed like `counts`
+    #[rhai_fn(name = "group_by", return_raw)]
+    pub fn array_group_by(ctx: NativeCallContext, a: Array, key_fn: FnPtr) -> RhaiRes<Map> {
+        super::group_by(&ctx, &mut a.into_iter().map(Ok), key_fn)
+    }
+
+    #[rhai_fn(name = "contains", name = "in", pure)]
+    pub fn int_set_contains(set: &mut SharedSet<INT>, v: INT) -> bool {
+        set.borrow().contains(&v)
+    }
+
+    #[rhai_fn(name = "insert")]
+    pub fn int_set_insert(set: &mut SharedSet<INT>, v: INT) -> bool {
+        set.borrow_mut().insert(v)
+    }
+
+    #[rhai_fn(name = "len", pure)]
+    pub fn int_set_len(set: &mut SharedSet<INT>) -> INT {
+        set.borrow().len().try_into().unwrap()
+    }
+
+    #[rhai_fn(name = "clone", pure)]
+    pub fn int_set_clone(set: &mut SharedSet<INT>) -> SharedSet<INT> {
+        super::new_set(set.borrow().clone())
+    }
+
+    /// The values in ascending order
+    #[rhai_fn(name = "to_array", pure)]
+    pub fn int_set_to_array(set: &mut SharedSet<INT>) -> Array {
+        let mut values: Vec<_> = set.borrow().iter().copied().collect();
+        values.sort_unstable();
+        values.into_iter().map(Dynamic::from).collect()
+    }
+
+    #[rhai_fn(name = "contains", name = "in", pure)]
+    pub fn str_set_contains(set: &mut SharedSet<ImmutableString>, v: ImmutableString) -> bool {
+        set.borrow().contains(&v)
+    }
+
+    #[rhai_fn(name = "insert")]
+    pub fn str_set_insert(set: &mut SharedSet<ImmutableString>, v: ImmutableString) -> bool {
+        set.borrow_mut().insert(v)
+    }
+
+    #[rhai_fn(name = "len", pure)]
+    pub fn str_set_len(set: &mut SharedSet<ImmutableString>) -> INT {
+        set.borrow().len().try_into().unwrap()
+    }
+
+    #[rhai_fn(name = "clone", pure)]
+    pub fn str_set_clone(set: &mut SharedSet<ImmutableString>) -> SharedSet<ImmutableString> {
+        super::new_set(set.borrow().clone())
+    }
+
+    /// The values in ascending order
+    #[rhai_fn(name = "to_array", pure)]
+    pub fn str_set_to_array(set: &mut SharedSet<ImmutableString>) -> Array {
+        let mut values: Vec<_> = set.borrow().iter().cloned().collect();
+        values.sort_unstable();
+        values.into_iter().map(Dynamic::from).collect()
+    }
+
+    /// The values in ascending order
+    #[rhai_fn(name = "to_array", pure)]
+    pub fn int_int_set_to_array(set: &mut SharedSet<(INT, INT)>) -> Array {
+        let mut values: Vec<_> = set.borrow().iter().copied().collect();
+        values.sort_unstable();
+        values.into_iter().map(Dynamic::from).collect()
+    }
+}
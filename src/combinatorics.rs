@@ -0,0 +1,213 @@
+use crate::*;
+
+/// The index lists of the `k`-combinations of `n` items in lexicographic order
+pub struct Combinations {
+    n: usize,
+    indices: Vec<usize>,
+    done: bool,
+}
+
+impl Combinations {
+    pub fn new(n: usize, k: usize) -> Self {
+        Self {
+            n,
+            indices: (0..k).collect(),
+            done: k > n,
+        }
+    }
+}
+
+impl Iterator for Combinations {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let ret = self.indices.clone();
+        let k = self.indices.len();
+        // The rightmost index that is not yet at its last position moves up by one, the
+        // ones after it restart right behind it
+        match (0..k).rev().find(|&i| self.indices[i] < self.n - k + i) {
+            Some(i) => {
+                self.indices[i] += 1;
+                for j in i + 1..k {
+                    self.indices[j] = self.indices[j - 1] + 1;
+                }
+            }
+            None => self.done = true,
+        }
+        Some(ret)
+    }
+}
+
+/// The index lists of the `k`-permutations of `n` items in lexicographic order
+pub struct Permutations {
+    k: usize,
+    indices: Vec<usize>,
+    done: bool,
+}
+
+impl Permutations {
+    pub fn new(n: usize, k: usize) -> Self {
+        Self {
+            k,
+            indices: (0..n).collect(),
+            done: k > n,
+        }
+    }
+}
+
+/// Rearrange `v` into the next greater permutation, false if it already was the greatest
+fn next_permutation(v: &mut [usize]) -> bool {
+    let Some(i) = (1..v.len()).rev().find(|&i| v[i - 1] < v[i]) else {
+        return false;
+    };
+    let j = (i..v.len()).rev().find(|&j| v[j] > v[i - 1]).unwrap();
+    v.swap(i - 1, j);
+    v[i..].reverse();
+    true
+}
+
+impl Iterator for Permutations {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let ret = self.indices[..self.k].to_vec();
+        // With the unused indices in descending order the next permutation of all of them
+        // is the first one that changes the first `k`
+        self.indices[self.k..].reverse();
+        self.done = !next_permutation(&mut self.indices);
+        Some(ret)
+    }
+}
+
+/// The index lists of all subsets of `n` items, the smaller subsets first
+pub fn powerset(n: usize) -> impl Iterator<Item = Vec<usize>> {
+    (0..=n).flat_map(move |k| Combinations::new(n, k))
+}
+
+/// Lazily map the items at each list of `indices`
+pub fn map_picks<T: Clone + 'static, R: 'static>(
+    items: Vec<T>,
+    indices: impl Iterator<Item = Vec<usize>> + 'static,
+    f: impl Fn(Vec<T>) -> R + 'static,
+) -> DynIterator<R> {
+    DynIterator::new(indices.map(move |ix| f(ix.iter().map(|&i| items[i].clone()).collect())))
+}
+
+/// Lazily map every pair of an item of `a` and an item of `b`, `a` in the outer loop
+pub fn map_product<T: Clone + 'static, R: 'static>(
+    mut a: Vec<T>,
+    b: Vec<T>,
+    f: impl Fn(Vec<T>) -> R + 'static,
+) -> DynIterator<R> {
+    let (n, m) = (a.len(), b.len());
+    a.extend(b);
+    let indices = (0..n).flat_map(move |i| (n..n + m).map(move |j| vec![i, j]));
+    map_picks(a, indices, f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+        Combinations::new(n, k).collect()
+    }
+
+    fn permutations(n: usize, k: usize) -> Vec<Vec<usize>> {
+        Permutations::new(n, k).collect()
+    }
+
+    #[test]
+    fn combinations_in_order() {
+        assert_eq!(
+            combinations(4, 2),
+            [[0, 1], [0, 2], [0, 3], [1, 2], [1, 3], [2, 3]]
+        );
+        assert_eq!(combinations(3, 3), [[0, 1, 2]]);
+        assert_eq!(combinations(3, 0), [Vec::<usize>::new()]);
+        assert_eq!(combinations(0, 0), [Vec::<usize>::new()]);
+        assert!(combinations(2, 3).is_empty());
+        assert!(combinations(0, 1).is_empty());
+        assert_eq!(Combinations::new(10, 4).count(), 210);
+    }
+
+    #[test]
+    fn permutations_in_order() {
+        assert_eq!(
+            permutations(3, 3),
+            [
+                [0, 1, 2],
+                [0, 2, 1],
+                [1, 0, 2],
+                [1, 2, 0],
+                [2, 0, 1],
+                [2, 1, 0]
+            ]
+        );
+        assert_eq!(
+            permutations(3, 2),
+            [[0, 1], [0, 2], [1, 0], [1, 2], [2, 0], [2, 1]]
+        );
+        assert_eq!(permutations(3, 1), [[0], [1], [2]]);
+        assert_eq!(permutations(3, 0), [Vec::<usize>::new()]);
+        assert_eq!(permutations(0, 0), [Vec::<usize>::new()]);
+        assert!(permutations(2, 3).is_empty());
+        assert!(permutations(0, 1).is_empty());
+        assert_eq!(Permutations::new(6, 3).count(), 120);
+    }
+
+    #[test]
+    fn powerset_smaller_first() {
+        let sets: Vec<_> = powerset(3).collect();
+        assert_eq!(
+            sets,
+            [
+                vec![],
+                vec![0],
+                vec![1],
+                vec![2],
+                vec![0, 1],
+                vec![0, 2],
+                vec![1, 2],
+                vec![0, 1, 2]
+            ]
+        );
+        assert_eq!(powerset(0).collect::<Vec<_>>(), [Vec::<usize>::new()]);
+    }
+
+    fn collect<T>(it: DynIterator<T>) -> Vec<T> {
+        it.map(|v| v.unwrap()).collect()
+    }
+
+    #[test]
+    fn products() {
+        assert_eq!(
+            collect(map_product(vec!['a', 'b'], vec!['x', 'y', 'z'], |v| v)),
+            [
+                ['a', 'x'],
+                ['a', 'y'],
+                ['a', 'z'],
+                ['b', 'x'],
+                ['b', 'y'],
+                ['b', 'z']
+            ]
+        );
+        assert!(collect(map_product(vec![], vec![1, 2], |v| v)).is_empty());
+        assert!(collect(map_product(vec![1, 2], vec![], |v| v)).is_empty());
+    }
+
+    #[test]
+    fn picks() {
+        let items = vec!["a", "b", "c"];
+        assert_eq!(
+            collect(map_picks(items, Combinations::new(3, 2), |v| v.concat())),
+            ["ab", "ac", "bc"]
+        );
+    }
+}
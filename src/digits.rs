@@ -0,0 +1,277 @@
+use crate::*;
+
+pub use self::digits::*;
+
+/// The digits of a number system in order of their value, digit `i` is worth `i - offset`.
+/// With an offset of 0 negative numbers get a `-` sign, otherwise the base is balanced and
+/// needs both negative and positive digits.
+pub struct Digits {
+    chars: Vec<char>,
+    offset: usize,
+}
+
+impl Digits {
+    pub fn new(digits: &str, offset: INT) -> Result<Self, String> {
+        let chars: Vec<char> = digits.chars().collect();
+        if chars.len() < 2 {
+            return Err(format!("A base needs at least 2 digits, got {digits:?}"));
+        }
+        if let Some(c) = chars
+            .iter()
+            .enumerate()
+            .find_map(|(i, c)| chars[..i].contains(c).then_some(c))
+        {
+            return Err(format!("Digit {c:?} appears twice in {digits:?}"));
+        }
+        let offset = usize::try_from(offset)
+            .map_err(|_| format!("Offset {offset} is negative for {digits:?}"))?;
+        if offset != 0 && offset + 1 >= chars.len() {
+            return Err(format!(
+                "Offset {offset} leaves no positive digits in {digits:?}"
+            ));
+        }
+        Ok(Self { chars, offset })
+    }
+
+    /// The digits of a balanced base centered on the middle digit, or the one before the
+    /// middle for an even number of digits. It takes 3 digits to have a negative, a zero
+    /// and a positive one.
+    pub fn balanced(digits: &str) -> Result<Self, String> {
+        let count = digits.chars().count();
+        if count < 3 {
+            return Err(format!(
+                "A balanced base needs at least 3 digits, got {digits:?}"
+            ));
+        }
+        Self::new(digits, ((count - 1) / 2) as INT)
+    }
+
+    /// The digits `0-9a-z` of base `radix`
+    pub fn radix(radix: INT) -> Result<Self, String> {
+        const DIGITS: &str = "0123456789abcdefghijklmnopqrstuvwxyz";
+        if !(2..=36).contains(&radix) {
+            return Err(format!("Invalid radix {radix}, expected 2 to 36"));
+        }
+        Self::new(&DIGITS[..radix as usize], 0)
+    }
+
+    pub fn format(&self, n: INT) -> String {
+        let base = self.chars.len() as i128;
+        let offset = self.offset as i128;
+        let mut n = n as i128;
+        let mut ret = Vec::new();
+        if self.offset == 0 && n < 0 {
+            ret.push('-');
+            n = -n;
+        }
+        let start = ret.len();
+        loop {
+            let i = (n + offset).rem_euclid(base);
+            ret.push(self.chars[i as usize]);
+            n = (n - (i - offset)) / base;
+            if n == 0 {
+                break;
+            }
+        }
+        ret[start..].reverse();
+        ret.into_iter().collect()
+    }
+
+    pub fn parse(&self, s: &str) -> Result<INT, String> {
+        let (negative, body) = match s.strip_prefix('-') {
+            Some(body) if self.offset == 0 && !self.chars.contains(&'-') => (true, body),
+            _ => (false, s),
+        };
+        if body.is_empty() {
+            return Err(format!("No digits in {s:?}"));
+        }
+        let overflow = || format!("Overflow parsing {s:?}");
+        // Wider than `INT`, the leading digits of a balanced number can overshoot the range
+        // of the whole number
+        let mut ret: i128 = 0;
+        for c in body.chars() {
+            let i = self
+                .chars
+                .iter()
+                .position(|&d| d == c)
+                .ok_or_else(|| format!("Invalid digit {c:?} in {s:?}"))?;
+            ret = ret
+                .checked_mul(self.chars.len() as i128)
+                .and_then(|n| n.checked_add(i as i128 - self.offset as i128))
+                .ok_or_else(overflow)?;
+        }
+        if negative {
+            ret = -ret;
+        }
+        INT::try_from(ret).map_err(|_| overflow())
+    }
+}
+
+fn parse_error(ctx: &NativeCallContext, msg: String) -> Box<EvalAltResult> {
+    Box::new(EvalAltResult::ErrorArithmetic(msg, ctx.position()))
+}
+
+#[export_module]
+mod digits {
+    /// Write `n` with `digits`, ordered by their value from 0, negative numbers start with `-`
+    #[rhai_fn(return_raw)]
+    pub fn to_base(ctx: NativeCallContext, n: INT, digits: &str) -> RhaiRes<String> {
+        let digits = Digits::new(digits, 0).map_err(|e| runtime_error(&ctx, e))?;
+        Ok(digits.format(n))
+    }
+
+    /// Parse the number written with `digits`, see `to_base`
+    #[rhai_fn(return_raw)]
+    pub fn from_base(ctx: NativeCallContext, s: &str, digits: &str) -> RhaiRes<INT> {
+        let digits = Digits::new(digits, 0).map_err(|e| runtime_error(&ctx, e))?;
+        digits.parse(s).map_err(|e| super::parse_error(&ctx, e))
+    }
+
+    /// Write `n` in the balanced base of `digits`, where the middle digit is worth 0, like
+    /// `"=-012"` for -2 to 2
+    #[rhai_fn(return_raw)]
+    pub fn to_balanced_base(ctx: NativeCallContext, n: INT, digits: &str) -> RhaiRes<String> {
+        let digits = Digits::balanced(digits).map_err(|e| runtime_error(&ctx, e))?;
+        Ok(digits.format(n))
+    }
+
+    /// Write `n` with `digits` where the first digit is worth `-offset`
+    #[rhai_fn(name = "to_balanced_base", return_raw)]
+    pub fn to_balanced_base_offset(
+        ctx: NativeCallContext,
+        n: INT,
+        digits: &str,
+        offset: INT,
+    ) -> RhaiRes<String> {
+        let digits = Digits::new(digits, offset).map_err(|e| runtime_error(&ctx, e))?;
+        Ok(digits.format(n))
+    }
+
+    /// Parse the number written in a balanced base, see `to_balanced_base`
+    #[rhai_fn(return_raw)]
+    pub fn from_balanced_base(ctx: NativeCallContext, s: &str, digits: &str) -> RhaiRes<INT> {
+        let digits = Digits::balanced(digits).map_err(|e| runtime_error(&ctx, e))?;
+        digits.parse(s).map_err(|e| super::parse_error(&ctx, e))
+    }
+
+    #[rhai_fn(name = "from_balanced_base", return_raw)]
+    pub fn from_balanced_base_offset(
+        ctx: NativeCallContext,
+        s: &str,
+        digits: &str,
+        offset: INT,
+    ) -> RhaiRes<INT> {
+        let digits = Digits::new(digits, offset).map_err(|e| runtime_error(&ctx, e))?;
+        digits.parse(s).map_err(|e| super::parse_error(&ctx, e))
+    }
+
+    /// Parse `s` in base `radix` from 2 to 36, letters can be upper or lower case. Replaces
+    /// the built-in version to report overflow and invalid digits at the call.
+    #[rhai_fn(return_raw)]
+    pub fn parse_int(ctx: NativeCallContext, s: &str, radix: INT) -> RhaiRes<INT> {
+        let digits = Digits::radix(radix).map_err(|e| super::parse_error(&ctx, e))?;
+        digits
+            .parse(&s.trim().to_ascii_lowercase())
+            .map_err(|e| super::parse_error(&ctx, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNAFU: &str = "=-012";
+
+    #[test]
+    fn balanced_round_trip() {
+        let digits = Digits::balanced(SNAFU).unwrap();
+        assert_eq!(digits.format(1747), "1=-0-2");
+        assert_eq!(digits.parse("1=-0-2"), Ok(1747));
+        assert_eq!(digits.format(0), "0");
+        assert_eq!(digits.format(-3), "-2");
+        assert_eq!(digits.parse("-2"), Ok(-3));
+        for n in [INT::MIN, INT::MIN + 1, -1, 1, INT::MAX - 1, INT::MAX] {
+            assert_eq!(digits.parse(&digits.format(n)), Ok(n), "{n}");
+        }
+    }
+
+    #[test]
+    fn plain_round_trip() {
+        for radix in [2, 10, 16, 36] {
+            let digits = Digits::radix(radix).unwrap();
+            for n in [INT::MIN, -1, 0, 1, INT::MAX] {
+                assert_eq!(
+                    digits.parse(&digits.format(n)),
+                    Ok(n),
+                    "{n} in base {radix}"
+                );
+            }
+        }
+        let hex = Digits::radix(16).unwrap();
+        assert_eq!(hex.format(-255), "-ff");
+        assert_eq!(hex.format(INT::MIN), "-8000000000000000");
+    }
+
+    #[test]
+    fn offset_digits() {
+        // Digits worth -1 to 3
+        let digits = Digits::new("abcde", 1).unwrap();
+        assert_eq!(digits.parse("ca"), Ok(4));
+        assert_eq!(digits.format(4), "ca");
+        assert_eq!(digits.format(-1), "a");
+    }
+
+    #[test]
+    fn overflow() {
+        let dec = Digits::radix(10).unwrap();
+        assert_eq!(dec.parse("9223372036854775807"), Ok(INT::MAX));
+        assert_eq!(dec.parse("-9223372036854775808"), Ok(INT::MIN));
+        for s in [
+            "9223372036854775808",
+            "-9223372036854775809",
+            &"9".repeat(40),
+        ] {
+            assert_eq!(dec.parse(s), Err(format!("Overflow parsing {s:?}")));
+        }
+        let snafu = Digits::balanced(SNAFU).unwrap();
+        let too_big = format!("1{}", "0".repeat(28));
+        assert_eq!(
+            snafu.parse(&too_big),
+            Err(format!("Overflow parsing {too_big:?}"))
+        );
+    }
+
+    #[test]
+    fn invalid_digits() {
+        assert_eq!(
+            Digits::new("0120", 0).err(),
+            Some("Digit '0' appears twice in \"0120\"".into())
+        );
+        assert_eq!(
+            Digits::new("0", 0).err(),
+            Some("A base needs at least 2 digits, got \"0\"".into())
+        );
+        assert_eq!(
+            Digits::new("012", 2).err(),
+            Some("Offset 2 leaves no positive digits in \"012\"".into())
+        );
+        assert!(Digits::new("012", -1).is_err());
+        assert_eq!(
+            Digits::balanced("-+").err(),
+            Some("A balanced base needs at least 3 digits, got \"-+\"".into())
+        );
+        let dec = Digits::radix(10).unwrap();
+        assert_eq!(dec.parse("12a"), Err("Invalid digit 'a' in \"12a\"".into()));
+        assert_eq!(dec.parse("-"), Err("No digits in \"-\"".into()));
+    }
+
+    #[test]
+    fn invalid_radix() {
+        for radix in [-2, 0, 1, 37] {
+            assert_eq!(
+                Digits::radix(radix).err(),
+                Some(format!("Invalid radix {radix}, expected 2 to 36"))
+            );
+        }
+    }
+}
@@ -1,23 +1,90 @@
-use crate::*;
+use crate::{
+    aoc_data::SharedAocData,
+    dynamic_image::{Col, Pixel, Row},
+    line::Line,
+    *,
+};
 
 pub use self::dyn_iterator::*;
 
 #[export_module]
 mod dyn_iterator {
-    #[derive(Clone)]
+    /// Shared handle to a lazy iterator, each item can be an error that stops a `for` loop
     pub struct DynIterator<T> {
-        it: Shared<Locked<Box<dyn Iterator<Item = T>>>>,
+        it: Shared<Locked<Box<dyn Iterator<Item = RhaiRes<T>>>>>,
+        /// The item looked at by `peek`, only `peekable` iterators have the slot
+        peeked: Option<Shared<Locked<Option<RhaiRes<T>>>>>,
+    }
+
+    // Not derived since that would require `T: Clone` for what is only a shared handle
+    impl<T> Clone for DynIterator<T> {
+        fn clone(&self) -> Self {
+            Self {
+                it: self.it.clone(),
+                peeked: self.peeked.clone(),
+            }
+        }
     }
 
     impl<T: 'static> DynIterator<T> {
         pub fn new(it: impl Iterator<Item = T> + 'static) -> Self {
+            Self::new_fallible(it.map(Ok))
+        }
+
+        pub fn new_fallible(it: impl Iterator<Item = RhaiRes<T>> + 'static) -> Self {
             Self {
                 it: Shared::new(Locked::new(Box::new(it))),
+                peeked: None,
+            }
+        }
+
+        /// The same iterator with a slot for `peek`, clones share the slot
+        pub fn into_peekable(self) -> Self {
+            Self {
+                it: self.it,
+                peeked: Some(self.peeked.unwrap_or_default()),
             }
         }
 
+        /// Map the items, passing on errors unchanged
+        pub fn map_ok<U: 'static>(self, mut f: impl FnMut(T) -> U + 'static) -> DynIterator<U> {
+            DynIterator::new_fallible(self.map(move |v| v.map(&mut f)))
+        }
+
+        /// Map the items with a fallible function, passing on errors unchanged
+        pub fn try_map<U: 'static>(
+            self,
+            mut f: impl FnMut(T) -> RhaiRes<U> + 'static,
+        ) -> DynIterator<U> {
+            DynIterator::new_fallible(self.map(move |v| v.and_then(&mut f)))
+        }
+
         pub fn into_skip(self, ctx: &NativeCallContext, count: INT) -> RhaiRes<DynIterator<T>> {
-            Ok(DynIterator::new(self.skip(try_from(ctx, count)?)))
+            Ok(DynIterator::new_fallible(self.skip(try_from(ctx, count)?)))
+        }
+    }
+
+    impl<T: rhai::Variant + Clone> DynIterator<T> {
+        /// The next item without consuming it, `()` at the end. An error is returned right
+        /// away instead of being kept for `next`.
+        pub fn peek(&self, ctx: &NativeCallContext) -> RhaiRes<Dynamic> {
+            let slot = self
+                .peeked
+                .as_ref()
+                .ok_or_else(|| runtime_error(ctx, "Only a peekable() iterator can peek"))?;
+            let mut slot = slot.borrow_mut();
+            if slot.is_none() {
+                *slot = self.it.borrow_mut().next();
+            }
+            match slot.take() {
+                None => Ok(Dynamic::UNIT),
+                Some(Ok(v)) => {
+                    let ret = Dynamic::from(v.clone());
+                    *slot = Some(Ok(v));
+                    Ok(ret)
+                }
+                Some(Err(e)) => Err(e),
+            }
         }
     }
 
@@ -26,7 +93,7 @@ mod dyn_iterator {
         data: DynIterator<ImmutableString>,
         c: char,
     ) -> DynIterator<Vec<ImmutableString>> {
-        DynIterator::new(data.map(move |v| v.split(c).map(|s| s.into()).collect()))
+        data.map_ok(move |v| v.split(c).map(|s| s.into()).collect())
     }
 
     #[rhai_fn(name = "split_once")]
@@ -34,30 +101,660 @@ mod dyn_iterator {
         data: DynIterator<ImmutableString>,
         c: char,
     ) -> DynIterator<(ImmutableString, ImmutableString)> {
-        DynIterator::new(data.map(move |v| {
+        data.map_ok(move |v| {
             v.split_once(c)
                 .map(|(a, b)| (a.into(), b.into()))
                 .unwrap_or((v, "".into()))
-        }))
+        })
     }
 
     #[rhai_fn(name = "split")]
     pub fn dyn_strvec_split_by_char(
         data: DynIterator<Vec<ImmutableString>>,
         c: char,
-    ) -> DynIterator<Vec<Dynamic>> {
-        DynIterator::new(data.map(move |v| {
+    ) -> DynIterator<Array> {
+        data.map_ok(move |v| {
             v.iter()
                 .map(|s| s.split(c).map(ImmutableString::from).collect())
                 .collect()
-        }))
+        })
+    }
+
+    /// All the integers of each line
+    #[rhai_fn(name = "ints")]
+    pub fn str_ints(
+        ctx: NativeCallContext,
+        data: DynIterator<ImmutableString>,
+    ) -> DynIterator<SharedIntArray> {
+        let pos = ctx.position();
+        data.try_map(move |line| {
+            IntArray::parse_all(&line)
+                .map(IntArray::new_shared)
+                .map_err(|e| Box::new(EvalAltResult::ErrorArithmetic(e, pos)))
+        })
     }
 
     impl<T> Iterator for DynIterator<T> {
-        type Item = T;
+        type Item = RhaiRes<T>;
 
         fn next(&mut self) -> Option<Self::Item> {
+            if let Some(v) = self.peeked.as_ref().and_then(|p| p.borrow_mut().take()) {
+                return Some(v);
+            }
             self.it.borrow_mut().next()
         }
     }
 }
+
+thread_local! {
+    /// The engine running the script, lazy iterators call their closures long after the
+    /// native call that created them has returned
+    static ENGINE: RefCell<Option<Shared<rhai::Engine>>> = const { RefCell::new(None) };
+}
+
+/// Makes `engine` available to the closures of lazy iterators until it is dropped
+pub struct EngineGuard(Option<Shared<rhai::Engine>>);
+
+impl EngineGuard {
+    pub fn enter(engine: Shared<rhai::Engine>) -> Self {
+        Self(ENGINE.with(|cur| cur.borrow_mut().replace(engine)))
+    }
+}
+
+impl Drop for EngineGuard {
+    fn drop(&mut self) {
+        ENGINE.with(|cur| *cur.borrow_mut() = self.0.take());
+    }
+}
+
+/// A script closure that can still be called after the native call that got it returned.
+/// The context store is a volatile API of the `internals` feature, which is why Cargo.toml
+/// pins the exact rhai version.
+#[allow(deprecated)]
+struct Callback {
+    fn_ptr: rhai::FnPtr,
+    store: rhai::NativeCallContextStore,
+}
+
+#[allow(deprecated)]
+impl Callback {
+    fn new(ctx: &NativeCallContext, fn_ptr: rhai::FnPtr) -> Self {
+        Self {
+            fn_ptr,
+            store: ctx.store_data(),
+        }
+    }
+
+    fn call(&self, args: impl AsMut<[Dynamic]>) -> RhaiRes<Dynamic> {
+        let engine = ENGINE.with(|cur| cur.borrow().clone()).ok_or_else(|| {
+            Box::new(EvalAltResult::ErrorRuntime(
+                "Lazy iterators can only call closures while the script runs".into(),
+                self.store.pos,
+            ))
+        })?;
+        self.fn_ptr
+            .call_raw(&self.store.create_context(&engine), None, args)
+    }
+
+    fn call_bool(&self, args: impl AsMut<[Dynamic]>) -> RhaiRes<bool> {
+        let ret = self.call(args)?;
+        ret.as_bool().map_err(|typ| {
+            Box::new(EvalAltResult::ErrorMismatchOutputType(
+                "bool".into(),
+                typ.into(),
+                self.store.pos,
+            ))
+        })
+    }
+}
+
+/// Calls `$m!(T)` for the item type `T` of every `DynIterator` the scripts can get
+macro_rules! for_item_types {
+    ($m:ident) => {
+        $m!(ImmutableString);
+        $m!(Blob);
+        $m!((ImmutableString, ImmutableString));
+        $m!(Vec<ImmutableString>);
+        $m!(Array);
+        $m!(Dynamic);
+        $m!(SharedIntArray);
+        $m!(SharedAocData);
+        $m!(Row);
+        $m!(Col);
+        $m!(Pixel);
+        $m!(Line);
+    };
+}
+
+/// Iterate an array, a range or any `DynIterator` as dynamic values
+fn iter_dynamic(value: Dynamic) -> Option<DynIterator<Dynamic>> {
+    if value.is::<Array>() {
+        return Some(DynIterator::new(value.cast::<Array>().into_iter()));
+    }
+    if value.is::<std::ops::Range<INT>>() {
+        let range = value.cast::<std::ops::Range<INT>>();
+        return Some(DynIterator::new(range.map(Dynamic::from)));
+    }
+    if value.is::<std::ops::RangeInclusive<INT>>() {
+        let range = value.cast::<std::ops::RangeInclusive<INT>>();
+        return Some(DynIterator::new(range.map(Dynamic::from)));
+    }
+    macro_rules! try_type {
+        (Dynamic) => {
+            if value.is::<DynIterator<Dynamic>>() {
+                return Some(value.cast());
+            }
+        };
+        ($t:ty) => {
+            if value.is::<DynIterator<$t>>() {
+                return Some(value.cast::<DynIterator<$t>>().map_ok(Dynamic::from));
+            }
+        };
+    }
+    for_item_types!(try_type);
+    None
+}
+
+fn not_iterable(ctx: &NativeCallContext, value: &Dynamic) -> Box<EvalAltResult> {
+    mismatching_data_type(ctx, "array, range or iterator", value.type_name())
+}
+
+/// Add `f` to `module` with the parameter names and types shown by `functions`
+fn set_fn<A, R, S, F>(module: &mut rhai::Module, name: &str, params: &[String], f: F)
+where
+    R: rhai::Variant + Clone,
+    F: rhai::RegisterNativeFunction<A, R, RhaiRes<S>>,
+{
+    let hash = module.set_native_fn(name, f);
+    module.update_fn_metadata(hash, params);
+}
+
+/// Whether `a` and `b` are the same value, `None` if scripts have no equality for the type
+/// of `a`. `Dynamic` has no `PartialEq` and the string form of custom types is only their
+/// type name.
+fn same_value(a: &Dynamic, b: &Dynamic) -> Option<bool> {
+    macro_rules! compare {
+        ($($t:ty),*) => {$(
+            if let Some(a) = a.read_lock::<$t>() {
+                return Some(b.read_lock::<$t>().is_some_and(|b| *a == *b));
+            }
+        )*};
+    }
+    compare!((), INT, ImmutableString, char, bool, (INT, INT), Blob);
+    if let Some(a) = a.read_lock::<Array>() {
+        let Some(b) = b.read_lock::<Array>() else {
+            return Some(false);
+        };
+        let mut same = a.len() == b.len();
+        for (a, b) in a.iter().zip(b.iter()) {
+            same &= same_value(a, b)?;
+        }
+        return Some(same);
+    }
+    if let Some(a) = a.read_lock::<SharedIntArray>() {
+        let Some(b) = b.read_lock::<SharedIntArray>() else {
+            return Some(false);
+        };
+        return Some(a.borrow().values() == b.borrow().values());
+    }
+    if let Some(a) = a.read_lock::<Line>() {
+        return Some(b.read_lock::<Line>().is_some_and(|b| a.text() == b.text()));
+    }
+    None
+}
+
+/// Lazily cut `items` into runs, `starts_run(v)` is asked about every item in order and
+/// a true answer starts a new run with `v`
+fn runs(
+    mut items: impl Iterator<Item = RhaiRes<Dynamic>> + 'static,
+    mut starts_run: impl FnMut(&Dynamic) -> RhaiRes<bool> + 'static,
+) -> DynIterator<Array> {
+    let mut pending = None;
+    DynIterator::new_fallible(std::iter::from_fn(move || {
+        let mut run: Array = pending.take().into_iter().collect();
+        loop {
+            let v = match items.next() {
+                None if run.is_empty() => return None,
+                None => return Some(Ok(run)),
+                Some(v) => v,
+            };
+            let starts = v.and_then(|v| Ok((starts_run(&v)?, v)));
+            match starts {
+                Err(e) => return Some(Err(e)),
+                Ok((true, v)) if !run.is_empty() => {
+                    pending = Some(v);
+                    return Some(Ok(run));
+                }
+                Ok((_, v)) => run.push(v),
+            }
+        }
+    }))
+}
+
+/// A `starts_run` for `runs` that starts a new run whenever the key changes, keys that
+/// cannot be compared are an error at `pos`
+fn key_changes(
+    pos: Position,
+    mut key: impl FnMut(&Dynamic) -> RhaiRes<Dynamic>,
+) -> impl FnMut(&Dynamic) -> RhaiRes<bool> {
+    let mut last: Option<Dynamic> = None;
+    move |v| {
+        let k = key(v)?;
+        let changed = match &last {
+            None => true,
+            Some(last) => !same_value(last, &k).ok_or_else(|| {
+                Box::new(EvalAltResult::ErrorMismatchDataType(
+                    "a value that can be compared".into(),
+                    k.type_name().into(),
+                    pos,
+                ))
+            })?,
+        };
+        last = Some(k);
+        Ok(changed)
+    }
+}
+
+/// Make `DynIterator<T>` iterable and add the closure combinators for it, `typ` is the name
+/// of `T`
+fn add_item_type<T: rhai::Variant + Clone>(module: &mut rhai::Module, typ: &str) {
+    module.set_iterable_result::<DynIterator<T>, T>();
+
+    let it = format!("it: DynIterator<{typ}>");
+    let sig = |params: &[&str], ret: &str| -> Vec<String> {
+        std::iter::once(it.clone())
+            .chain(params.iter().map(|p| p.to_string()))
+            .chain([ret.replace("<T>", &format!("<{typ}>"))])
+            .collect()
+    };
+
+    set_fn(
+        module,
+        "map",
+        &sig(&["f: FnPtr"], "DynIterator<Dynamic>"),
+        |ctx: NativeCallContext, it: DynIterator<T>, f: rhai::FnPtr| {
+            let f = Callback::new(&ctx, f);
+            Ok(it.try_map(move |v| f.call([Dynamic::from(v)])))
+        },
+    );
+    set_fn(
+        module,
+        "filter",
+        &sig(&["f: FnPtr"], "DynIterator<T>"),
+        |ctx: NativeCallContext, it: DynIterator<T>, f: rhai::FnPtr| {
+            let f = Callback::new(&ctx, f);
+            Ok(DynIterator::new_fallible(it.filter_map(move |v| {
+                let keep = v
+                    .as_ref()
+                    .map_or(Ok(true), |v| f.call_bool([Dynamic::from(v.clone())]));
+                match keep {
+                    Ok(true) => Some(v),
+                    Ok(false) => None,
+                    Err(e) => Some(Err(e)),
+                }
+            })))
+        },
+    );
+    set_fn(
+        module,
+        "take",
+        &sig(&["count: i64"], "DynIterator<T>"),
+        |ctx: NativeCallContext, it: DynIterator<T>, count: INT| {
+            Ok(DynIterator::new_fallible(it.take(try_from(&ctx, count)?)))
+        },
+    );
+    set_fn(
+        module,
+        "skip",
+        &sig(&["count: i64"], "DynIterator<T>"),
+        |ctx: NativeCallContext, it: DynIterator<T>, count: INT| it.into_skip(&ctx, count),
+    );
+    set_fn(
+        module,
+        "take_while",
+        &sig(&["f: FnPtr"], "DynIterator<T>"),
+        |ctx: NativeCallContext, it: DynIterator<T>, f: rhai::FnPtr| {
+            let f = Callback::new(&ctx, f);
+            let mut done = false;
+            Ok(DynIterator::new_fallible(it.map_while(move |v| {
+                if done {
+                    return None;
+                }
+                let v = match v {
+                    Ok(v) => v,
+                    Err(e) => return Some(Err(e)),
+                };
+                match f.call_bool([Dynamic::from(v.clone())]) {
+                    Ok(true) => Some(Ok(v)),
+                    Ok(false) => None,
+                    Err(e) => {
+                        done = true;
+                        Some(Err(e))
+                    }
+                }
+            })))
+        },
+    );
+    set_fn(
+        module,
+        "skip_while",
+        &sig(&["f: FnPtr"], "DynIterator<T>"),
+        |ctx: NativeCallContext, it: DynIterator<T>, f: rhai::FnPtr| {
+            let f = Callback::new(&ctx, f);
+            let mut skipping = true;
+            Ok(DynIterator::new_fallible(it.filter_map(move |v| {
+                if !skipping {
+                    return Some(v);
+                }
+                let skip = v
+                    .as_ref()
+                    .map_or(Ok(false), |v| f.call_bool([Dynamic::from(v.clone())]));
+                match skip {
+                    Ok(true) => None,
+                    Ok(false) => {
+                        skipping = false;
+                        Some(v)
+                    }
+                    Err(e) => Some(Err(e)),
+                }
+            })))
+        },
+    );
+    set_fn(
+        module,
+        "enumerate",
+        &sig(&[], "DynIterator<Array>"),
+        |it: DynIterator<T>| {
+            let mut index: INT = -1;
+            Ok(it.map_ok(move |v| {
+                index += 1;
+                vec![Dynamic::from(index), Dynamic::from(v)]
+            }))
+        },
+    );
+    set_fn(
+        module,
+        "zip",
+        &sig(&["other: Dynamic"], "DynIterator<Array>"),
+        |ctx: NativeCallContext, it: DynIterator<T>, other: Dynamic| {
+            let other = iter_dynamic(other.clone()).ok_or_else(|| not_iterable(&ctx, &other))?;
+            Ok(DynIterator::new_fallible(
+                it.zip(other).map(|(a, b)| Ok(vec![Dynamic::from(a?), b?])),
+            ))
+        },
+    );
+    set_fn(
+        module,
+        "chain",
+        &sig(&["other: Dynamic"], "DynIterator<Dynamic>"),
+        |ctx: NativeCallContext, it: DynIterator<T>, other: Dynamic| {
+            let other = iter_dynamic(other.clone()).ok_or_else(|| not_iterable(&ctx, &other))?;
+            Ok(DynIterator::new_fallible(
+                it.map_ok(Dynamic::from).chain(other),
+            ))
+        },
+    );
+    set_fn(
+        module,
+        "flat_map",
+        &sig(&["f: FnPtr"], "DynIterator<Dynamic>"),
+        |ctx: NativeCallContext, it: DynIterator<T>, f: rhai::FnPtr| {
+            let pos = ctx.position();
+            let f = Callback::new(&ctx, f);
+            Ok(DynIterator::new_fallible(
+                it.map(move |v| {
+                    let inner = f.call([Dynamic::from(v?)])?;
+                    iter_dynamic(inner.clone()).ok_or_else(|| {
+                        Box::new(EvalAltResult::ErrorMismatchOutputType(
+                            "array, range or iterator".into(),
+                            inner.type_name().into(),
+                            pos,
+                        ))
+                    })
+                })
+                .flat_map(|inner| match inner {
+                    Ok(inner) => DynIterator::new_fallible(inner),
+                    Err(e) => DynIterator::new_fallible(std::iter::once(Err(e))),
+                }),
+            ))
+        },
+    );
+    set_fn(
+        module,
+        "fold",
+        &sig(&["init: Dynamic", "f: FnPtr"], "Dynamic"),
+        |ctx: NativeCallContext, it: DynIterator<T>, init: Dynamic, f: rhai::FnPtr| {
+            let mut acc = init;
+            for v in it {
+                acc = f.call_raw(&ctx, None, [acc, Dynamic::from(v?)])?;
+            }
+            Ok(acc)
+        },
+    );
+    set_fn(
+        module,
+        "any",
+        &sig(&["f: FnPtr"], "bool"),
+        |ctx: NativeCallContext, it: DynIterator<T>, f: rhai::FnPtr| {
+            let f = Callback::new(&ctx, f);
+            for v in it {
+                if f.call_bool([Dynamic::from(v?)])? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        },
+    );
+    set_fn(
+        module,
+        "all",
+        &sig(&["f: FnPtr"], "bool"),
+        |ctx: NativeCallContext, it: DynIterator<T>, f: rhai::FnPtr| {
+            let f = Callback::new(&ctx, f);
+            for v in it {
+                if !f.call_bool([Dynamic::from(v?)])? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        },
+    );
+    set_fn(
+        module,
+        "count",
+        &sig(&[], "i64"),
+        |ctx: NativeCallContext, it: DynIterator<T>| {
+            let mut count = 0;
+            for v in it {
+                v?;
+                count += 1;
+            }
+            try_from::<usize, INT, _>(&ctx, count)
+        },
+    );
+    set_fn(
+        module,
+        "find",
+        &sig(&["f: FnPtr"], "Dynamic"),
+        |ctx: NativeCallContext, it: DynIterator<T>, f: rhai::FnPtr| {
+            let f = Callback::new(&ctx, f);
+            for v in it {
+                let v = Dynamic::from(v?);
+                if f.call_bool([v.clone()])? {
+                    return Ok(v);
+                }
+            }
+            Ok(Dynamic::UNIT)
+        },
+    );
+    set_fn(
+        module,
+        "last",
+        &sig(&[], "Dynamic"),
+        |it: DynIterator<T>| {
+            let mut last = Dynamic::UNIT;
+            for v in it {
+                last = Dynamic::from(v?);
+            }
+            Ok(last)
+        },
+    );
+    set_fn(
+        module,
+        "collect",
+        &sig(&[], "Array"),
+        |it: DynIterator<T>| it.map(|v| v.map(Dynamic::from)).collect::<RhaiRes<Array>>(),
+    );
+    set_fn(
+        module,
+        "next",
+        &sig(&[], "Dynamic"),
+        |mut it: DynIterator<T>| {
+            it.next()
+                .map_or(Ok(Dynamic::UNIT), |v| v.map(Dynamic::from))
+        },
+    );
+    set_fn(
+        module,
+        "peekable",
+        &sig(&[], "DynIterator<T>"),
+        |it: DynIterator<T>| Ok(it.into_peekable()),
+    );
+    set_fn(
+        module,
+        "peek",
+        &sig(&[], "Dynamic"),
+        |ctx: NativeCallContext, it: DynIterator<T>| it.peek(&ctx),
+    );
+    set_fn(
+        module,
+        "chunk_by",
+        &sig(&["key_fn: FnPtr"], "DynIterator<Array>"),
+        |ctx: NativeCallContext, it: DynIterator<T>, key_fn: rhai::FnPtr| {
+            let key_fn = Callback::new(&ctx, key_fn);
+            Ok(runs(
+                it.map(|v| v.map(Dynamic::from)),
+                key_changes(ctx.position(), move |v| key_fn.call([v.clone()])),
+            ))
+        },
+    );
+    set_fn(
+        module,
+        "split_when",
+        &sig(&["f: FnPtr"], "DynIterator<Array>"),
+        |ctx: NativeCallContext, it: DynIterator<T>, f: rhai::FnPtr| {
+            let f = Callback::new(&ctx, f);
+            Ok(runs(it.map(|v| v.map(Dynamic::from)), move |v| {
+                f.call_bool([v.clone()])
+            }))
+        },
+    );
+    set_fn(
+        module,
+        "dedup",
+        &sig(&[], "DynIterator<T>"),
+        |ctx: NativeCallContext, it: DynIterator<T>| {
+            let mut changed = key_changes(ctx.position(), |v| Ok(v.clone()));
+            Ok(DynIterator::new_fallible(it.filter_map(move |v| {
+                match v.and_then(|v| Ok((changed(&Dynamic::from(v.clone()))?, v))) {
+                    Ok((true, v)) => Some(Ok(v)),
+                    Ok((false, _)) => None,
+                    Err(e) => Some(Err(e)),
+                }
+            })))
+        },
+    );
+    set_fn(
+        module,
+        "run_lengths",
+        &sig(&[], "DynIterator<Array>"),
+        |ctx: NativeCallContext, it: DynIterator<T>| {
+            let runs = runs(
+                it.map(|v| v.map(Dynamic::from)),
+                key_changes(ctx.position(), |v| Ok(v.clone())),
+            );
+            Ok(runs.map_ok(|run| {
+                let len = run.len() as INT;
+                vec![run.into_iter().next().unwrap(), Dynamic::from(len)]
+            }))
+        },
+    );
+    set_fn(
+        module,
+        "collect_ints",
+        &sig(&[], "SharedIntArray"),
+        |ctx: NativeCallContext, it: DynIterator<T>| {
+            collect::collect_ints(&ctx, &mut it.map(|v| v.map(Dynamic::from)))
+        },
+    );
+    set_fn(
+        module,
+        "collect_set",
+        &sig(&[], "Dynamic"),
+        |ctx: NativeCallContext, it: DynIterator<T>| {
+            collect::collect_set(&ctx, &mut it.map(|v| v.map(Dynamic::from)))
+        },
+    );
+    set_fn(
+        module,
+        "collect_map",
+        &sig(&["key_fn: FnPtr", "value_fn: FnPtr"], "Map"),
+        |ctx: NativeCallContext, it: DynIterator<T>, key_fn: rhai::FnPtr, value_fn: rhai::FnPtr| {
+            collect::collect_map(
+                &ctx,
+                &mut it.map(|v| v.map(Dynamic::from)),
+                key_fn,
+                value_fn,
+            )
+        },
+    );
+    set_fn(
+        module,
+        "counts",
+        &sig(&[], "Map"),
+        |ctx: NativeCallContext, it: DynIterator<T>| {
+            collect::counts(&ctx, &mut it.map(|v| v.map(Dynamic::from)))
+        },
+    );
+    set_fn(
+        module,
+        "group_by",
+        &sig(&["key_fn: FnPtr"], "Map"),
+        |ctx: NativeCallContext, it: DynIterator<T>, key_fn: rhai::FnPtr| {
+            collect::group_by(&ctx, &mut it.map(|v| v.map(Dynamic::from)), key_fn)
+        },
+    );
+}
+
+fn item_type_name(stringified: &str) -> String {
+    stringified.replace(' ', "")
+}
+
+/// The iterators over every item type with their closure combinators, `export_module` can
+/// only export functions for concrete types
+pub fn item_type_module() -> rhai::Module {
+    let mut module = rhai::Module::new();
+    macro_rules! add {
+        ($t:ty) => {
+            add_item_type::<$t>(&mut module, &item_type_name(stringify!($t)));
+        };
+    }
+    for_item_types!(add);
+    module
+}
+
+/// The name and type of each `DynIterator<T>` in the item type registry
+#[cfg(test)]
+pub fn registered_iterators() -> Vec<(String, std::any::TypeId)> {
+    let mut ret = Vec::new();
+    macro_rules! add {
+        ($t:ty) => {
+            ret.push((
+                format!("DynIterator<{}>", item_type_name(stringify!($t))),
+                std::any::TypeId::of::<DynIterator<$t>>(),
+            ));
+        };
+    }
+    for_item_types!(add);
+    ret
+}
@@ -6,12 +6,18 @@ pub use self::dynamic_image::*;
 mod dynamic_image {
     pub type SharedDynImg = Shared<Locked<DynamicImage>>;
 
+    iterable_types!(Row => Pixel, Col => Pixel);
+
     pub struct DynamicImage {
         width: usize,
         data: Vec<u32>,
     }
 
     impl DynamicImage {
+        pub fn new_shared(width: usize, data: Vec<u32>) -> SharedDynImg {
+            Shared::new(Locked::new(DynamicImage { width, data }))
+        }
+
         fn dim(&self) -> (usize, usize) {
             (self.width, self.data.len() / self.width)
         }
@@ -237,24 +243,6 @@ mod dynamic_image {
         Ok(Row { start, img })
     }
 
-    #[rhai_fn(return_raw, name = "skip")]
-    pub fn rows_skip(
-        ctx: NativeCallContext,
-        it: DynIterator<Row>,
-        count: INT,
-    ) -> RhaiRes<DynIterator<Row>> {
-        it.into_skip(&ctx, count)
-    }
-
-    #[rhai_fn(return_raw, name = "skip")]
-    pub fn pixels_skip(
-        ctx: NativeCallContext,
-        it: DynIterator<Pixel>,
-        count: INT,
-    ) -> RhaiRes<DynIterator<Pixel>> {
-        it.into_skip(&ctx, count)
-    }
-
     pub fn rrows(img: SharedDynImg) -> DynIterator<Row> {
         DynIterator::new(iter_rows(img, 0, usize::MAX).rev())
     }
@@ -331,7 +319,7 @@ mod dynamic_image {
     }
 
     impl IntoIterator for Row {
-        type Item = Pixel;
+        type Item = RhaiRes<Pixel>;
         type IntoIter = DynIterator<Pixel>;
 
         fn into_iter(self) -> Self::IntoIter {
@@ -369,7 +357,7 @@ mod dynamic_image {
     }
 
     impl IntoIterator for Col {
-        type Item = Pixel;
+        type Item = RhaiRes<Pixel>;
         type IntoIter = DynIterator<Pixel>;
 
         fn into_iter(self) -> Self::IntoIter {
@@ -9,6 +9,21 @@ pub fn index_not_found(ctx: &NativeCallContext, index: impl Into<Dynamic>) -> Bo
     ))
 }
 
+pub fn runtime_error(ctx: &NativeCallContext, msg: impl Into<String>) -> Box<EvalAltResult> {
+    Box::new(EvalAltResult::ErrorRuntime(
+        msg.into().into(),
+        ctx.position(),
+    ))
+}
+
+/// Attach the position of the native call to an error raised without a context
+pub fn at_call(ctx: &NativeCallContext, mut err: Box<EvalAltResult>) -> Box<EvalAltResult> {
+    if err.position().is_none() {
+        err.set_position(ctx.position());
+    }
+    err
+}
+
 pub fn mismatching_data_type(
     ctx: &NativeCallContext,
     req: impl Into<String>,
@@ -33,3 +48,70 @@ where
         ))
     })
 }
+
+pub fn fmt_location(src: &Path, pos: Position) -> String {
+    match (pos.line(), pos.position()) {
+        (Some(line), Some(col)) => format!("{}:{line}:{col}", src.display()),
+        (Some(line), None) => format!("{}:{line}", src.display()),
+        _ => src.display().to_string(),
+    }
+}
+
+/// Unwrap the nested function call and module errors so that each position is reported
+/// together with the file it belongs to, an error inside an imported library would
+/// otherwise point at a line in the day script.
+pub fn script_error(
+    mut err: Box<EvalAltResult>,
+    script_path: &Path,
+    module_dir: &Path,
+) -> eyre::Report {
+    let module_path = |name: &str| module_dir.join(name).with_extension("rhai");
+    let mut src = script_path.to_path_buf();
+    let mut frames = Vec::new();
+    loop {
+        match *err {
+            EvalAltResult::ErrorInFunctionCall(name, fn_src, inner, pos) => {
+                // Rhai folds nested script calls into one error named `inner @ 'src' < outer`,
+                // only the innermost function tells where the wrapped error happened.
+                let mut chain = name.split(" < ").map(|v| v.split_once(" @ '"));
+                let inner_src = match chain.next() {
+                    Some(Some((_, inner_src))) => inner_src.trim_end_matches('\'').to_string(),
+                    _ if !name.contains(" < ") => fn_src,
+                    _ => String::new(),
+                };
+                let names: Vec<_> = name
+                    .split(" < ")
+                    .map(|v| v.split_once(" @ '").map_or(v, |(v, _)| v))
+                    .collect();
+                frames.push(format!(
+                    "in {} called at {}",
+                    names.join(" < "),
+                    fmt_location(&src, pos)
+                ));
+                src = if inner_src.is_empty() {
+                    script_path.to_path_buf()
+                } else {
+                    module_path(&inner_src)
+                };
+                err = inner;
+            }
+            EvalAltResult::ErrorInModule(name, inner, pos) => {
+                frames.push(format!(
+                    "in {name:?} imported at {}",
+                    fmt_location(&src, pos)
+                ));
+                src = module_path(&name);
+                err = inner;
+            }
+            _ => break,
+        }
+    }
+
+    let pos = err.take_position();
+    let mut msg = format!("{err} at {}", fmt_location(&src, pos));
+    for frame in frames.iter().rev() {
+        msg.push_str("\n  ");
+        msg.push_str(frame);
+    }
+    eyre!(msg)
+}
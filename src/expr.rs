@@ -0,0 +1,421 @@
+use crate::*;
+
+pub use self::expr::*;
+
+#[derive(Clone, Copy)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+enum Node {
+    Const(INT),
+    Var(usize),
+    Neg(Box<Node>),
+    Bin(Op, Box<Node>, Box<Node>),
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    vars: &'a [ImmutableString],
+}
+
+impl<'a> Parser<'a> {
+    fn skip_ws(&mut self) {
+        while self
+            .text
+            .as_bytes()
+            .get(self.pos)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_ws();
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn unexpected(&self, expected: &str) -> String {
+        match self.text[self.pos..].chars().next() {
+            Some(c) => format!(
+                "Expected {expected} at column {} of {:?}, got {c:?}",
+                self.pos + 1,
+                self.text
+            ),
+            None => format!("Expected {expected} at the end of {:?}", self.text),
+        }
+    }
+
+    /// A sum of terms, or a term when `level` is 1
+    fn binary(&mut self, level: u8) -> Result<Node, String> {
+        let mut lhs = if level == 0 {
+            self.binary(1)?
+        } else {
+            self.unary()?
+        };
+        loop {
+            let op = match (level, self.peek()) {
+                (0, Some(b'+')) => Op::Add,
+                (0, Some(b'-')) => Op::Sub,
+                (1, Some(b'*')) => Op::Mul,
+                (1, Some(b'/')) => Op::Div,
+                (1, Some(b'%')) => Op::Rem,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            let rhs = if level == 0 {
+                self.binary(1)?
+            } else {
+                self.unary()?
+            };
+            lhs = Node::Bin(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        match self.peek() {
+            Some(b'-') => {
+                self.pos += 1;
+                Ok(Node::Neg(Box::new(self.unary()?)))
+            }
+            Some(b'(') => {
+                self.pos += 1;
+                let ret = self.binary(0)?;
+                if self.peek() != Some(b')') {
+                    return Err(self.unexpected("')'"));
+                }
+                self.pos += 1;
+                Ok(ret)
+            }
+            Some(b'0'..=b'9') => {
+                let start = self.pos;
+                while self
+                    .text
+                    .as_bytes()
+                    .get(self.pos)
+                    .is_some_and(u8::is_ascii_digit)
+                {
+                    self.pos += 1;
+                }
+                let num = &self.text[start..self.pos];
+                num.parse()
+                    .map(Node::Const)
+                    .map_err(|e| format!("Failed to parse {num} in {:?}: {e}", self.text))
+            }
+            Some(b) if b == b'_' || b.is_ascii_alphabetic() => {
+                let start = self.pos;
+                while self
+                    .text
+                    .as_bytes()
+                    .get(self.pos)
+                    .is_some_and(|&b| b == b'_' || b.is_ascii_alphanumeric())
+                {
+                    self.pos += 1;
+                }
+                let name = &self.text[start..self.pos];
+                self.vars
+                    .iter()
+                    .position(|v| v.as_str() == name)
+                    .map(Node::Var)
+                    .ok_or_else(|| format!("Unknown variable {name:?} in {:?}", self.text))
+            }
+            _ => Err(self.unexpected("a number, variable or '('")),
+        }
+    }
+}
+
+/// Parse `text` into a tree over the variables in `vars`, which are referred to by index
+fn parse(text: &str, vars: &[ImmutableString]) -> Result<Node, String> {
+    let mut parser = Parser { text, pos: 0, vars };
+    let root = parser.binary(0)?;
+    if parser.peek().is_some() {
+        return Err(parser.unexpected("an operator"));
+    }
+    Ok(root)
+}
+
+impl Node {
+    fn eval(&self, args: &[INT], check_overflow: bool) -> Result<INT, String> {
+        let (op, lhs, rhs) = match self {
+            Node::Const(v) => return Ok(*v),
+            Node::Var(i) => return Ok(args[*i]),
+            Node::Neg(v) => {
+                let v = v.eval(args, check_overflow)?;
+                return match check_overflow {
+                    true => v.checked_neg().ok_or_else(|| format!("Overflow in -{v}")),
+                    false => Ok(v.wrapping_neg()),
+                };
+            }
+            Node::Bin(op, lhs, rhs) => (
+                *op,
+                lhs.eval(args, check_overflow)?,
+                rhs.eval(args, check_overflow)?,
+            ),
+        };
+        if matches!(op, Op::Div | Op::Rem) && rhs == 0 {
+            return Err(format!("Division by zero: {lhs} {} 0", op.symbol()));
+        }
+        if !check_overflow {
+            return Ok(match op {
+                Op::Add => lhs.wrapping_add(rhs),
+                Op::Sub => lhs.wrapping_sub(rhs),
+                Op::Mul => lhs.wrapping_mul(rhs),
+                Op::Div => lhs.wrapping_div(rhs),
+                Op::Rem => lhs.wrapping_rem(rhs),
+            });
+        }
+        match op {
+            Op::Add => lhs.checked_add(rhs),
+            Op::Sub => lhs.checked_sub(rhs),
+            Op::Mul => lhs.checked_mul(rhs),
+            Op::Div => lhs.checked_div(rhs),
+            Op::Rem => lhs.checked_rem(rhs),
+        }
+        .ok_or_else(|| format!("Overflow in {lhs} {} {rhs}", op.symbol()))
+    }
+}
+
+impl Op {
+    fn symbol(self) -> char {
+        match self {
+            Op::Add => '+',
+            Op::Sub => '-',
+            Op::Mul => '*',
+            Op::Div => '/',
+            Op::Rem => '%',
+        }
+    }
+}
+
+#[export_module]
+mod expr {
+    use rhai::Map;
+
+    /// Integer arithmetic compiled once so it can be evaluated without the script engine
+    #[derive(Clone)]
+    pub struct Expr {
+        text: ImmutableString,
+        root: Shared<super::Node>,
+        num_vars: usize,
+        check_overflow: bool,
+    }
+
+    impl Expr {
+        fn compile(
+            ctx: &NativeCallContext,
+            text: ImmutableString,
+            vars: Array,
+            check_overflow: bool,
+        ) -> RhaiRes<Self> {
+            let vars = vars
+                .into_iter()
+                .map(|v| {
+                    v.into_immutable_string()
+                        .map_err(|e| mismatching_data_type(ctx, "string", e))
+                })
+                .collect::<RhaiRes<Vec<_>>>()?;
+            let root = super::parse(&text, &vars).map_err(|e| runtime_error(ctx, e))?;
+            Ok(Self {
+                text,
+                root: Shared::new(root),
+                num_vars: vars.len(),
+                check_overflow,
+            })
+        }
+
+        fn eval(&self, ctx: &NativeCallContext, args: &[INT]) -> RhaiRes<INT> {
+            if args.len() != self.num_vars {
+                return Err(runtime_error(
+                    ctx,
+                    format!(
+                        "{:?} takes {} values, got {}",
+                        self.text,
+                        self.num_vars,
+                        args.len()
+                    ),
+                ));
+            }
+            self.root
+                .eval(args, self.check_overflow)
+                .map_err(|e| Box::new(EvalAltResult::ErrorArithmetic(e, ctx.position())))
+        }
+    }
+
+    /// Compile integer arithmetic with `+ - * / %` and parentheses over the named `vars`.
+    /// Overflow silently wraps around, `compile_expr("x * x", ["x"]).apply(9223372036854775807)`
+    /// is 1. Pass `#{check_overflow: true}` to make it an error instead.
+    #[rhai_fn(return_raw)]
+    pub fn compile_expr(
+        ctx: NativeCallContext,
+        text: ImmutableString,
+        vars: Array,
+    ) -> RhaiRes<Expr> {
+        Expr::compile(&ctx, text, vars, false)
+    }
+
+    /// Like `compile_expr(text, vars)`, `#{check_overflow: true}` in `options` makes an
+    /// overflow an error
+    #[rhai_fn(name = "compile_expr", return_raw)]
+    pub fn compile_expr_options(
+        ctx: NativeCallContext,
+        text: ImmutableString,
+        vars: Array,
+        options: Map,
+    ) -> RhaiRes<Expr> {
+        let mut check_overflow = false;
+        for (key, value) in options {
+            match key.as_str() {
+                "check_overflow" => {
+                    check_overflow = value
+                        .as_bool()
+                        .map_err(|e| mismatching_data_type(&ctx, "bool", e))?
+                }
+                _ => return Err(runtime_error(&ctx, format!("Unknown option {key:?}"))),
+            }
+        }
+        Expr::compile(&ctx, text, vars, check_overflow)
+    }
+
+    /// Evaluate an expression of a single variable
+    #[rhai_fn(pure, name = "apply", return_raw)]
+    pub fn apply_int(ctx: NativeCallContext, expr: &mut Expr, value: INT) -> RhaiRes<INT> {
+        expr.eval(&ctx, &[value])
+    }
+
+    /// Evaluate with the values of the variables in the order they were named
+    #[rhai_fn(pure, name = "apply", return_raw)]
+    pub fn apply_array(ctx: NativeCallContext, expr: &mut Expr, values: Array) -> RhaiRes<INT> {
+        let values = values
+            .into_iter()
+            .map(|v| {
+                v.as_int()
+                    .map_err(|e| mismatching_data_type(&ctx, "integer", e))
+            })
+            .collect::<RhaiRes<Vec<_>>>()?;
+        expr.eval(&ctx, &values)
+    }
+
+    #[rhai_fn(pure, name = "to_string")]
+    pub fn to_string(expr: &mut Expr) -> String {
+        expr.text.to_string()
+    }
+
+    #[rhai_fn(pure, name = "to_debug")]
+    pub fn to_debug(expr: &mut Expr) -> String {
+        format!("compile_expr({:?})", expr.text.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(text: &str, vars: &[&str], args: &[INT], check_overflow: bool) -> Result<INT, String> {
+        let vars: Vec<ImmutableString> = vars.iter().map(|&v| v.into()).collect();
+        parse(text, &vars)?.eval(args, check_overflow)
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        for (text, want) in [
+            ("2 + 3 * 4", 14),
+            ("2 * 3 + 4", 10),
+            ("10 - 2 - 3", 5),
+            ("100 / 10 / 5", 2),
+            ("17 % 5 * 2", 4),
+            ("(2 + 3) * 4", 20),
+            ("((7))", 7),
+            ("-3 * -(2 - 5)", -9),
+            ("--4", 4),
+            ("-7 / 2", -3),
+            ("-7 % 2", -1),
+        ] {
+            assert_eq!(eval(text, &[], &[], true), Ok(want), "{text}");
+        }
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!(eval("old * old", &["old"], &[7], true), Ok(49));
+        assert_eq!(
+            eval("a - b * c", &["a", "b", "c"], &[1, 2, 3], true),
+            Ok(-5)
+        );
+        assert_eq!(eval("b - a", &["a", "b"], &[1, 10], true), Ok(9));
+        assert_eq!(
+            eval("old + new", &["old"], &[1], true),
+            Err(r#"Unknown variable "new" in "old + new""#.into())
+        );
+    }
+
+    #[test]
+    fn division_by_zero() {
+        for check_overflow in [false, true] {
+            assert_eq!(
+                eval("x / (x - x)", &["x"], &[3], check_overflow),
+                Err("Division by zero: 3 / 0".into())
+            );
+            assert_eq!(
+                eval("x % 0", &["x"], &[3], check_overflow),
+                Err("Division by zero: 3 % 0".into())
+            );
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        let max = INT::MAX;
+        assert_eq!(eval("x * x", &["x"], &[max], false), Ok(1));
+        assert_eq!(
+            eval("x * x", &["x"], &[max], true),
+            Err(format!("Overflow in {max} * {max}"))
+        );
+        assert_eq!(eval("x + 1", &["x"], &[max], false), Ok(INT::MIN));
+        assert_eq!(
+            eval("x + 1", &["x"], &[max], true),
+            Err(format!("Overflow in {max} + 1"))
+        );
+        assert_eq!(eval("-x", &["x"], &[INT::MIN], false), Ok(INT::MIN));
+        assert_eq!(
+            eval("-x", &["x"], &[INT::MIN], true),
+            Err(format!("Overflow in -{}", INT::MIN))
+        );
+        assert_eq!(eval("x / -1", &["x"], &[INT::MIN], false), Ok(INT::MIN));
+        assert!(eval("x / -1", &["x"], &[INT::MIN], true).is_err());
+    }
+
+    #[test]
+    fn parse_errors() {
+        for (text, err) in [
+            (
+                "1 +",
+                r#"Expected a number, variable or '(' at the end of "1 +""#,
+            ),
+            (
+                "1 + * 2",
+                r#"Expected a number, variable or '(' at column 5 of "1 + * 2", got '*'"#,
+            ),
+            ("(1 + 2", r#"Expected ')' at the end of "(1 + 2""#),
+            (
+                "1 + 2)",
+                r#"Expected an operator at column 6 of "1 + 2)", got ')'"#,
+            ),
+            (
+                "1 2",
+                r#"Expected an operator at column 3 of "1 2", got '2'"#,
+            ),
+            ("", r#"Expected a number, variable or '(' at the end of """#),
+        ] {
+            assert_eq!(eval(text, &[], &[], false), Err(err.into()), "{text}");
+        }
+        assert!(eval("99999999999999999999", &[], &[], false)
+            .unwrap_err()
+            .starts_with("Failed to parse 99999999999999999999"));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::*;
+use rhai::{Engine, Module};
+
+/// Make the generated signature read like the script sees it, without the Rust side
+/// error wrapping and borrows.
+fn tidy_signature(sig: &str) -> String {
+    let mut ret = sig
+        .replace("&mut ", "")
+        .replace("(mut ", "(")
+        .replace(", mut ", ", ");
+    for (prefix, suffix) in [("RhaiRes<", ">"), ("Result<", ", Box<EvalAltResult>>")] {
+        if let Some((head, ret_type)) = ret.split_once(&format!(" -> {prefix}")) {
+            if let Some(ret_type) = ret_type.strip_suffix(suffix) {
+                ret = format!("{head} -> {}", ret_type.trim_end());
+            }
+        }
+    }
+    ret.trim_end_matches(" -> ()")
+        .replace("INT", "i64")
+        .replace("FLOAT", "f64")
+}
+
+/// The tidied signatures of the native functions for each module, sorted and without the
+/// modules that have none left. With a pattern only the functions whose signature or module
+/// name contains it are kept.
+fn function_list(modules: &[(&str, Module)], pattern: Option<&str>) -> Vec<(String, Vec<String>)> {
+    let pattern = pattern.map(str::to_lowercase);
+    let mut ret = Vec::new();
+    for (name, module) in modules {
+        let mut signatures: Vec<_> = module
+            .gen_fn_signatures()
+            .map(|sig| tidy_signature(&sig))
+            .filter(|sig| match &pattern {
+                Some(pattern) => {
+                    name.contains(pattern.as_str()) || sig.to_lowercase().contains(pattern)
+                }
+                None => true,
+            })
+            .collect();
+        if signatures.is_empty() {
+            continue;
+        }
+        signatures.sort();
+        ret.push((name.to_string(), signatures));
+    }
+    ret
+}
+
+/// Print the signature of every native function, grouped by the module that registers it,
+/// see `function_list`
+pub fn list_functions(modules: &[(&str, Module)], pattern: Option<&str>) {
+    for (name, signatures) in function_list(modules, pattern) {
+        println!("{name}:");
+        for sig in signatures {
+            println!("  {sig}");
+        }
+    }
+}
+
+/// Tidy the functions of a definitions file like `tidy_signature`, rhai only unwraps its own
+/// `RhaiResultOf` and writes `Dynamic` as `?`
+fn tidy_definitions(defs: &str) -> String {
+    let mut ret = String::new();
+    for line in defs.lines() {
+        let sig = ["fn ", "op "]
+            .into_iter()
+            .find_map(|kind| Some((kind, line.strip_prefix(kind)?.strip_suffix(';')?)));
+        match sig {
+            Some((kind, sig)) => {
+                let sig = tidy_signature(sig).replace("DynIterator<?>", "DynIterator<Dynamic>");
+                ret += &format!("{kind}{sig};");
+            }
+            None => ret += line,
+        }
+        ret.push('\n');
+    }
+    ret
+}
+
+/// Write a definitions file that the Rhai language server can load for completion. Only the
+/// native `modules` are included, not the generated `Line` forwarders that have no metadata.
+pub fn write_definitions(modules: Vec<(&str, Module)>, path: &Path) -> Result<()> {
+    let mut engine = Engine::new_raw();
+    for (_, module) in modules {
+        engine.register_global_module(module.into());
+    }
+    let defs = engine
+        .definitions()
+        .include_standard_packages(false)
+        .single_file();
+    std::fs::write(path, tidy_definitions(&defs))
+        .wrap_err_with(|| format!("Failed to write definitions to {path:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tidy_signatures() {
+        for (sig, want) in [
+            (
+                "next_line(data: &mut SharedAocData) -> RhaiRes<Dynamic>",
+                "next_line(data: SharedAocData) -> Dynamic",
+            ),
+            (
+                "index(line: Line, i: INT) -> RhaiRes<char >",
+                "index(line: Line, i: i64) -> char",
+            ),
+            (
+                "parse(s: &str) -> Result<FLOAT, Box<EvalAltResult>>",
+                "parse(s: &str) -> f64",
+            ),
+            (
+                "rewind(mut it: DynIterator<Line>) -> RhaiRes<()>",
+                "rewind(it: DynIterator<Line>)",
+            ),
+            ("op &=(Pixel, int) -> RhaiRes<() >", "op &=(Pixel, int)"),
+            ("len(a: Array) -> INT", "len(a: Array) -> i64"),
+        ] {
+            assert_eq!(tidy_signature(sig), want);
+        }
+    }
+
+    #[test]
+    fn function_lists() {
+        let modules = crate::native_modules();
+        let all = function_list(&modules, None);
+        assert!(all
+            .iter()
+            .all(|(_, sigs)| sigs.windows(2).all(|w| w[0] <= w[1])));
+        assert!(all
+            .iter()
+            .flat_map(|(_, sigs)| sigs)
+            .all(|sig| !sig.contains("RhaiRes") && !sig.contains("&mut ")));
+
+        // A module name keeps all of its functions, otherwise the signature has to match
+        let by_module = function_list(&modules, Some("digits"));
+        let (name, sigs) = by_module.iter().find(|(name, _)| name == "digits").unwrap();
+        let (_, all_sigs) = all.iter().find(|(n, _)| n == name).unwrap();
+        assert_eq!(sigs, all_sigs);
+        let by_sig = function_list(&modules, Some("PARSE_JSON"));
+        assert_eq!(by_sig.len(), 1);
+        assert_eq!(by_sig[0].1, ["parse_json(s: &str) -> Dynamic"]);
+        assert!(function_list(&modules, Some("no such function")).is_empty());
+    }
+
+    #[test]
+    fn definitions() {
+        let path = std::env::temp_dir().join("aoc-functions-test.d.rhai");
+        write_definitions(crate::native_modules(), &path).unwrap();
+        let defs = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(defs.starts_with("module static;"));
+        for line in defs.lines() {
+            for bad in ["RhaiRes", "Result<", "_: ?", "-> ;", "DynIterator<?>"] {
+                assert!(!line.contains(bad), "{line}");
+            }
+        }
+        assert!(defs.contains("fn next_line(data: SharedAocData) -> ?;"));
+    }
+}
@@ -6,7 +6,9 @@ pub use self::int_array::*;
 mod int_array {
     use super::*;
 
-    type SharedIntArray = Shared<Locked<IntArray>>;
+    pub type SharedIntArray = Shared<Locked<IntArray>>;
+
+    iterable_types!(Vec<INT>);
 
     #[derive(Default)]
     pub struct IntArray {
@@ -14,9 +16,43 @@ mod int_array {
     }
 
     impl IntArray {
-        fn new_shared(values: Vec<INT>) -> SharedIntArray {
+        pub fn new_shared(values: Vec<INT>) -> SharedIntArray {
             Shared::new(Locked::new(IntArray { values }))
         }
+
+        pub fn values(&self) -> &[INT] {
+            &self.values
+        }
+
+        /// Collect every integer in `text`. A `-` only makes the number negative when it
+        /// does not follow a letter or digit, so ranges like `2-4` are two positive numbers.
+        pub fn parse_all(text: &str) -> Result<Vec<INT>, String> {
+            let bytes = text.as_bytes();
+            let mut ret = Vec::new();
+            let mut i = 0;
+            while i < bytes.len() {
+                if !bytes[i].is_ascii_digit() {
+                    i += 1;
+                    continue;
+                }
+                let mut start = i;
+                if start > 0
+                    && bytes[start - 1] == b'-'
+                    && (start < 2 || !bytes[start - 2].is_ascii_alphanumeric())
+                {
+                    start -= 1;
+                }
+                while i < bytes.len() && bytes[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let num = &text[start..i];
+                ret.push(
+                    num.parse()
+                        .map_err(|e| format!("Failed to parse {num}: {e}"))?,
+                );
+            }
+            Ok(ret)
+        }
     }
 
     impl IntArray {
@@ -95,6 +131,106 @@ mod int_array {
         Ok(IntArray::new_shared(array.values[start..].to_vec()))
     }
 
+    #[rhai_fn(pure)]
+    pub fn to_array(array: &mut SharedIntArray) -> Array {
+        array
+            .borrow()
+            .values
+            .iter()
+            .copied()
+            .map(Dynamic::from)
+            .collect()
+    }
+
+    /// Every run of `len` consecutive values
+    #[rhai_fn(pure, return_raw)]
+    pub fn windows(
+        ctx: NativeCallContext,
+        array: &mut SharedIntArray,
+        len: INT,
+    ) -> RhaiRes<DynIterator<SharedIntArray>> {
+        let values = array.borrow().values.clone();
+        Ok(slices::map_windows(
+            values,
+            slices::slice_len(&ctx, len)?,
+            |w| IntArray::new_shared(w.to_vec()),
+        ))
+    }
+
+    /// `len` values at a time, the last chunk may be shorter
+    #[rhai_fn(pure, return_raw)]
+    pub fn chunks(
+        ctx: NativeCallContext,
+        array: &mut SharedIntArray,
+        len: INT,
+    ) -> RhaiRes<DynIterator<SharedIntArray>> {
+        let values = array.borrow().values.clone();
+        Ok(slices::map_chunks(
+            values,
+            slices::slice_len(&ctx, len)?,
+            |w| IntArray::new_shared(w.to_vec()),
+        ))
+    }
+
+    /// Every choice of `k` values in their original order
+    #[rhai_fn(pure, return_raw)]
+    pub fn combinations(
+        ctx: NativeCallContext,
+        array: &mut SharedIntArray,
+        k: INT,
+    ) -> RhaiRes<DynIterator<SharedIntArray>> {
+        let values = array.borrow().values.clone();
+        let indices = combinatorics::Combinations::new(values.len(), try_from(&ctx, k)?);
+        Ok(combinatorics::map_picks(
+            values,
+            indices,
+            IntArray::new_shared,
+        ))
+    }
+
+    /// Every arrangement of `k` of the values
+    #[rhai_fn(pure, return_raw)]
+    pub fn permutations(
+        ctx: NativeCallContext,
+        array: &mut SharedIntArray,
+        k: INT,
+    ) -> RhaiRes<DynIterator<SharedIntArray>> {
+        let values = array.borrow().values.clone();
+        let indices = combinatorics::Permutations::new(values.len(), try_from(&ctx, k)?);
+        Ok(combinatorics::map_picks(
+            values,
+            indices,
+            IntArray::new_shared,
+        ))
+    }
+
+    /// Every `[x, y]` with `x` from `array` and `y` from `other`
+    #[rhai_fn(pure)]
+    pub fn cartesian_product(
+        array: &mut SharedIntArray,
+        other: SharedIntArray,
+    ) -> DynIterator<SharedIntArray> {
+        let values = array.borrow().values.clone();
+        let other = other.borrow().values.clone();
+        combinatorics::map_product(values, other, IntArray::new_shared)
+    }
+
+    /// Every subset of the values, the smaller subsets first
+    #[rhai_fn(pure)]
+    pub fn powerset(array: &mut SharedIntArray) -> DynIterator<SharedIntArray> {
+        let values = array.borrow().values.clone();
+        let indices = combinatorics::powerset(values.len());
+        combinatorics::map_picks(values, indices, IntArray::new_shared)
+    }
+
+    /// Every `[x, y]` with `x` before `y` in the array
+    #[rhai_fn(pure)]
+    pub fn pairs(array: &mut SharedIntArray) -> DynIterator<SharedIntArray> {
+        let values = array.borrow().values.clone();
+        let indices = combinatorics::Combinations::new(values.len(), 2);
+        combinatorics::map_picks(values, indices, IntArray::new_shared)
+    }
+
     pub fn drain(array: &mut SharedIntArray) -> Vec<INT> {
         let mut array = array.borrow_mut();
         let ret = array.values.clone();
@@ -131,3 +267,37 @@ mod int_array {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minus_signs() {
+        for (text, want) in [
+            ("2-4,6-8", vec![2, 4, 6, 8]),
+            ("x=-3, y=4", vec![-3, 4]),
+            ("a -3 b", vec![-3]),
+            ("-1 --2 3-", vec![-1, -2, 3]),
+            ("a-3 b2-1", vec![3, 2, 1]),
+            ("no numbers", vec![]),
+        ] {
+            assert_eq!(IntArray::parse_all(text), Ok(want), "{text}");
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        assert_eq!(
+            IntArray::parse_all("1 99999999999999999999"),
+            Err(
+                "Failed to parse 99999999999999999999: number too large to fit in target type"
+                    .into()
+            )
+        );
+        assert_eq!(
+            IntArray::parse_all("-9223372036854775808"),
+            Ok(vec![INT::MIN])
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use crate::*;
+use std::any::TypeId;
+
+pub use self::line::*;
+
+#[export_module]
+mod line {
+    /// A line of input that remembers where it came from. It stands in for a string in
+    /// function calls and comparisons, but `switch` cases and map indexing only match real
+    /// strings, use `line.text` there.
+    #[derive(Clone)]
+    pub struct Line {
+        text: ImmutableString,
+        /// 1-based, counting from the start of the file even for sections
+        line_no: INT,
+        file: ImmutableString,
+    }
+
+    impl Line {
+        pub fn new(text: ImmutableString, line_no: INT, file: ImmutableString) -> Self {
+            Self {
+                text,
+                line_no,
+                file,
+            }
+        }
+
+        pub fn text(&self) -> &str {
+            &self.text
+        }
+
+        /// `file:line` like the locations in script errors
+        pub fn location(&self) -> String {
+            format!("{}:{}", self.file, self.line_no)
+        }
+
+        /// Report `err` of the native function `fn_name` as `in fn_name for line file:N`,
+        /// the source of the call stays the script
+        pub fn locate_error(
+            &self,
+            ctx: &NativeCallContext,
+            fn_name: &str,
+            err: Box<EvalAltResult>,
+        ) -> Box<EvalAltResult> {
+            Box::new(EvalAltResult::ErrorInFunctionCall(
+                format!("{fn_name} for line {}", self.location()),
+                String::new(),
+                at_call(ctx, err),
+                ctx.position(),
+            ))
+        }
+
+        /// Call the string version of `fn_name` with the text of the line in `args[index]`.
+        /// A function that changes its first argument in place changes the text of the line.
+        pub fn forward(
+            ctx: &NativeCallContext,
+            fn_name: &str,
+            index: usize,
+            args: &mut [&mut Dynamic],
+        ) -> RhaiRes<Dynamic> {
+            let line = args[index].read_lock::<Self>().unwrap().clone();
+            let mut text = Dynamic::from(line.text.clone());
+            let res = {
+                let (before, rest) = args.split_at_mut(index);
+                let mut args: Vec<&mut Dynamic> = before
+                    .iter_mut()
+                    .map(|a| &mut **a)
+                    .chain([&mut text])
+                    .chain(rest[1..].iter_mut().map(|a| &mut **a))
+                    .collect();
+                ctx.call_native_fn_raw(fn_name, true, &mut args)
+            };
+            let ret = res.map_err(|e| line.locate_error(ctx, fn_name, e))?;
+            if index == 0 {
+                if let Ok(text) = text.into_immutable_string() {
+                    if text != line.text {
+                        args[0].write_lock::<Self>().unwrap().text = text;
+                    }
+                }
+            }
+            Ok(ret)
+        }
+    }
+
+    #[rhai_fn(get = "text", pure)]
+    pub fn get_text(line: &mut Line) -> ImmutableString {
+        line.text.clone()
+    }
+
+    #[rhai_fn(get = "line_no", pure)]
+    pub fn get_line_no(line: &mut Line) -> INT {
+        line.line_no
+    }
+
+    #[rhai_fn(get = "file", pure)]
+    pub fn get_file(line: &mut Line) -> ImmutableString {
+        line.file.clone()
+    }
+
+    #[rhai_fn(pure)]
+    pub fn to_string(line: &mut Line) -> ImmutableString {
+        line.text.clone()
+    }
+
+    #[rhai_fn(pure)]
+    pub fn to_debug(line: &mut Line) -> String {
+        format!("{:?} @ {}", line.text.as_str(), line.location())
+    }
+
+    /// The character at `index`, negative indices count from the end
+    #[rhai_fn(index_get, pure, return_raw)]
+    pub fn index_get(ctx: NativeCallContext, line: &mut Line, index: INT) -> RhaiRes<char> {
+        let count = line.text.chars().count();
+        let i = if index < 0 {
+            usize::try_from(index.unsigned_abs())
+                .ok()
+                .and_then(|i| count.checked_sub(i))
+        } else {
+            usize::try_from(index).ok()
+        };
+        i.and_then(|i| line.text.chars().nth(i))
+            .ok_or_else(|| line.locate_error(&ctx, "index", index_not_found(&ctx, index)))
+    }
+
+    #[rhai_fn(name = "==", pure)]
+    pub fn eq_str(line: &mut Line, s: &str) -> bool {
+        line.text == s
+    }
+
+    #[rhai_fn(name = "==")]
+    pub fn str_eq(s: &str, line: Line) -> bool {
+        line.text == s
+    }
+
+    #[rhai_fn(name = "==", pure)]
+    pub fn eq_line(line: &mut Line, other: Line) -> bool {
+        line.text == other.text
+    }
+
+    #[rhai_fn(name = "!=", pure)]
+    pub fn ne_str(line: &mut Line, s: &str) -> bool {
+        line.text != s
+    }
+
+    #[rhai_fn(name = "!=")]
+    pub fn str_ne(s: &str, line: Line) -> bool {
+        line.text != s
+    }
+
+    #[rhai_fn(name = "!=", pure)]
+    pub fn ne_line(line: &mut Line, other: Line) -> bool {
+        line.text != other.text
+    }
+
+    #[rhai_fn(name = "+", pure)]
+    pub fn add_str(line: &mut Line, s: &str) -> String {
+        format!("{}{s}", line.text)
+    }
+
+    #[rhai_fn(name = "+")]
+    pub fn str_add(s: &str, line: Line) -> String {
+        format!("{s}{}", line.text)
+    }
+}
+
+/// Name and parameter types of a signature from `gen_fn_signatures`
+fn parse_signature(sig: &str) -> Option<(&str, Vec<&str>)> {
+    let (name, rest) = sig.split_once('(')?;
+    let params = match rest.rsplit_once(") -> ") {
+        Some((params, _)) => params,
+        None => rest.strip_suffix(')')?,
+    };
+    let types = params
+        .split(", ")
+        .filter(|p| !p.is_empty())
+        .map(|p| p.split_once(": ").map_or(p, |(_, typ)| typ))
+        .collect();
+    Some((name, types))
+}
+
+fn is_string_type(typ: &str) -> bool {
+    matches!(
+        typ.trim_start_matches("&mut ").trim_start_matches('&'),
+        "str" | "ImmutableString" | "String"
+    )
+}
+
+/// Make every string function the engine knows take a `Line` in place of any of its string
+/// arguments, the other arguments are matched when the string version is called
+pub fn string_forwards(engine: &rhai::Engine) -> rhai::Module {
+    let signatures = engine.gen_fn_signatures(true);
+    let parsed: Vec<_> = signatures
+        .iter()
+        .filter_map(|s| parse_signature(s))
+        .collect();
+    // Positions where a function already takes a `Line` of its own
+    let own: HashSet<(&str, usize, usize)> = parsed
+        .iter()
+        .flat_map(|(name, types)| {
+            types
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| t.ends_with("Line"))
+                .map(|(i, _)| (*name, types.len(), i))
+        })
+        .collect();
+    let mut module = rhai::Module::new();
+    let mut seen = HashSet::new();
+    for (name, types) in &parsed {
+        // `print` and `debug` already handle any value through `to_string` and `to_debug`
+        if matches!(*name, "print" | "debug") {
+            continue;
+        }
+        for (index, _) in types.iter().enumerate().filter(|(_, t)| is_string_type(t)) {
+            let key = (*name, types.len(), index);
+            if own.contains(&key) || !seen.insert(key) {
+                continue;
+            }
+            let mut arg_types = vec![TypeId::of::<Dynamic>(); types.len()];
+            arg_types[index] = TypeId::of::<Line>();
+            let fn_name = name.to_string();
+            module.set_raw_fn(
+                *name,
+                rhai::FnNamespace::Global,
+                rhai::FnAccess::Public,
+                &arg_types,
+                move |ctx, args| Line::forward(&ctx, &fn_name, index, args),
+            );
+        }
+    }
+    module
+}
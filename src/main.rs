@@ -1,28 +1,75 @@
-use crate::{dyn_iterator::DynIterator, error::*, tuple_extras::index_tup2};
-use eyre::{ensure, eyre, Result, WrapErr};
-use rhai::{plugin::*, Array, Blob, EvalAltResult, Locked, Shared, FLOAT, INT};
+use crate::{
+    aoc_data::SharedAocData,
+    dyn_iterator::DynIterator,
+    error::*,
+    int_array::{IntArray, SharedIntArray},
+    tuple_extras::index_tup2,
+};
+use eyre::{bail, ensure, eyre, Result, WrapErr};
+use rhai::{
+    module_resolvers::FileModuleResolver, plugin::*, Array, Blob, EvalAltResult, Locked, Shared,
+    FLOAT, INT,
+};
 use std::{
-    collections::HashSet,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
     fmt::{Debug, Formatter, Result as FmtResult},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
+/// Declares the types an `#[export_module]` exposes for `for` loops, `native_modules`
+/// registers them together with the functions of the module. `DynIterator`s are
+/// registered for all their item types by `dyn_iterator::item_type_module`.
+macro_rules! iterable_types {
+    (@set $module:ident, $t:ty => $item:ty) => {
+        $module.set_iterable_result::<$t, $item>();
+    };
+    (@set $module:ident, $t:ty) => {
+        $module.set_iterable::<$t>();
+    };
+    ($($t:ty $(=> $item:ty)?),* $(,)?) => {
+        pub(crate) fn register_iterables(module: &mut rhai::Module) {
+            $(iterable_types!(@set module, $t $(=> $item)?);)*
+        }
+    };
+}
+
 mod aoc_data;
 mod assert;
 mod blob_extras;
+mod case_file;
+mod check;
+mod collect;
+mod combinatorics;
+mod digits;
 mod dyn_iterator;
 mod dynamic_image;
 mod error;
+mod expr;
+mod functions;
 mod int_array;
+mod line;
+mod parsing;
+mod regex_extras;
+mod scan;
+#[cfg(test)]
+mod script_tests;
+mod slices;
+mod text_block;
 
 type SharedSet<T> = Shared<Locked<HashSet<T>>>;
 
 #[export_module]
 mod string_extras {
-    pub fn chunks(a: ImmutableString, len: INT) -> DynIterator<ImmutableString> {
-        let len = usize::try_from(len).unwrap();
+    #[rhai_fn(return_raw)]
+    pub fn chunks(
+        ctx: NativeCallContext,
+        a: ImmutableString,
+        len: INT,
+    ) -> RhaiRes<DynIterator<ImmutableString>> {
+        let len = slices::slice_len(&ctx, len)?;
         let mut i = 0;
-        DynIterator::new(std::iter::from_fn(move || {
+        Ok(DynIterator::new(std::iter::from_fn(move || {
             if let Some((end, _)) = a[i..].char_indices().nth(len) {
                 let end = end + i;
                 let ret = a[i..end].into();
@@ -35,6 +82,20 @@ mod string_extras {
             } else {
                 None
             }
+        })))
+    }
+
+    /// Every run of `len` consecutive characters
+    #[rhai_fn(return_raw)]
+    pub fn windows(
+        ctx: NativeCallContext,
+        a: ImmutableString,
+        len: INT,
+    ) -> RhaiRes<DynIterator<ImmutableString>> {
+        let len = slices::slice_len(&ctx, len)?;
+        let bounds = a.char_indices().map(|(i, _)| i).chain([a.len()]).collect();
+        Ok(slices::map_windows(bounds, len + 1, move |w| {
+            a[w[0]..w[len]].into()
         }))
     }
 
@@ -46,6 +107,14 @@ mod string_extras {
             Dynamic::UNIT
         }
     }
+
+    /// All the integers in the string, see `data.ints()`
+    #[rhai_fn(return_raw)]
+    pub fn ints(ctx: NativeCallContext, s: &str) -> RhaiRes<SharedIntArray> {
+        IntArray::parse_all(s)
+            .map(IntArray::new_shared)
+            .map_err(|e| Box::new(EvalAltResult::ErrorArithmetic(e, ctx.position())))
+    }
 }
 
 #[export_module]
@@ -83,6 +152,60 @@ fn iter_ints<'a>(
 
 #[export_module]
 mod array_extras {
+    /// Every run of `len` consecutive elements
+    #[rhai_fn(name = "windows", return_raw)]
+    pub fn array_windows(
+        ctx: NativeCallContext,
+        a: Array,
+        len: INT,
+    ) -> RhaiRes<DynIterator<Array>> {
+        Ok(slices::map_windows(
+            a,
+            slices::slice_len(&ctx, len)?,
+            <[_]>::to_vec,
+        ))
+    }
+
+    /// `len` elements at a time, the last chunk may be shorter
+    #[rhai_fn(name = "chunks", return_raw)]
+    pub fn array_chunks(ctx: NativeCallContext, a: Array, len: INT) -> RhaiRes<DynIterator<Array>> {
+        Ok(slices::map_chunks(
+            a,
+            slices::slice_len(&ctx, len)?,
+            <[_]>::to_vec,
+        ))
+    }
+
+    /// Every choice of `k` elements in their original order
+    #[rhai_fn(return_raw)]
+    pub fn combinations(ctx: NativeCallContext, a: Array, k: INT) -> RhaiRes<DynIterator<Array>> {
+        let indices = combinatorics::Combinations::new(a.len(), try_from(&ctx, k)?);
+        Ok(combinatorics::map_picks(a, indices, Array::from))
+    }
+
+    /// Every arrangement of `k` of the elements
+    #[rhai_fn(return_raw)]
+    pub fn permutations(ctx: NativeCallContext, a: Array, k: INT) -> RhaiRes<DynIterator<Array>> {
+        let indices = combinatorics::Permutations::new(a.len(), try_from(&ctx, k)?);
+        Ok(combinatorics::map_picks(a, indices, Array::from))
+    }
+
+    /// Every `[x, y]` with `x` from `a` and `y` from `other`
+    pub fn cartesian_product(a: Array, other: Array) -> DynIterator<Array> {
+        combinatorics::map_product(a, other, Array::from)
+    }
+
+    /// Every subset of the elements, the smaller subsets first
+    pub fn powerset(a: Array) -> DynIterator<Array> {
+        let indices = combinatorics::powerset(a.len());
+        combinatorics::map_picks(a, indices, Array::from)
+    }
+
+    /// Every `[x, y]` with `x` before `y` in the array
+    pub fn pairs(a: Array) -> DynIterator<Array> {
+        let indices = combinatorics::Combinations::new(a.len(), 2);
+        combinatorics::map_picks(a, indices, Array::from)
+    }
 
     #[rhai_fn(pure, return_raw)]
     pub fn sum(ctx: NativeCallContext, a: &mut rhai::Array) -> RhaiRes<INT> {
@@ -258,45 +381,149 @@ mod tuple_extras {
     }
 }
 
-fn run_script(day: u8, data_name: &str) -> Result<[String; 2]> {
-    let dir = PathBuf::from(format!("solutions/day-{day:02}"));
-    let script_path = dir.join("script.rhai");
-    let data_path = dir.join(data_name);
+/// Directory that `import` statements in the scripts are resolved against
+const MODULE_DIR: &str = "solutions";
+
+/// All the native modules exposed to the scripts together with their names
+fn native_modules() -> Vec<(&'static str, rhai::Module)> {
+    macro_rules! with_iterables {
+        ($name:ident) => {{
+            let mut module = exported_module!($name);
+            $name::register_iterables(&mut module);
+            module
+        }};
+    }
+
+    vec![
+        ("aoc_data", exported_module!(aoc_data)),
+        ("int_array", with_iterables!(int_array)),
+        ("blob_extras", exported_module!(blob_extras)),
+        ("dyn_iterator", exported_module!(dyn_iterator)),
+        ("string_extras", exported_module!(string_extras)),
+        ("parsing", exported_module!(parsing)),
+        ("regex_extras", exported_module!(regex_extras)),
+        ("int_extras", exported_module!(int_extras)),
+        ("digits", exported_module!(digits)),
+        ("assert", exported_module!(assert)),
+        ("dynamic_image", with_iterables!(dynamic_image)),
+        ("array_extras", exported_module!(array_extras)),
+        ("tuple_extras", exported_module!(tuple_extras)),
+        ("scan", exported_module!(scan)),
+        ("text_block", exported_module!(text_block)),
+        ("expr", exported_module!(expr)),
+        ("collect", exported_module!(collect)),
+        ("line", exported_module!(line)),
+        ("dyn_iterator_items", dyn_iterator::item_type_module()),
+    ]
+}
 
+fn create_engine() -> rhai::Engine {
     let mut engine = rhai::Engine::new();
-    let mut scope = rhai::Scope::new();
-
-    scope.push("data", aoc_data::AocData::load(data_path)?);
 
     engine.set_fast_operators(false);
 
-    engine.register_global_module(exported_module!(aoc_data).into());
-    engine.register_global_module(exported_module!(int_array).into());
-    engine.register_global_module(exported_module!(blob_extras).into());
-    engine.register_global_module(exported_module!(dyn_iterator).into());
-    engine.register_global_module(exported_module!(string_extras).into());
-    engine.register_global_module(exported_module!(int_extras).into());
-    engine.register_global_module(exported_module!(assert).into());
-    engine.register_global_module(exported_module!(dynamic_image).into());
-    engine.register_global_module(exported_module!(array_extras).into());
-    engine.register_global_module(exported_module!(tuple_extras).into());
-
-    // ToDo: Is there no magic to register this in the module?
-    engine.register_iterator::<DynIterator<ImmutableString>>();
-    engine.register_iterator::<DynIterator<Blob>>();
-    engine.register_iterator::<Vec<INT>>();
-    engine.register_iterator::<DynIterator<(ImmutableString, ImmutableString)>>();
-    engine.register_iterator::<DynIterator<Vec<ImmutableString>>>();
-    engine.register_iterator::<DynIterator<Vec<Dynamic>>>();
-    engine.register_iterator::<DynIterator<dynamic_image::Row>>();
-    engine.register_iterator::<DynIterator<dynamic_image::Col>>();
-    engine.register_iterator::<DynIterator<dynamic_image::Pixel>>();
-    engine.register_iterator::<dynamic_image::Row>();
-    engine.register_iterator::<dynamic_image::Col>();
+    // Scripts can share helpers with `import "lib/parsing" as parsing;`, each run gets a
+    // fresh resolver so edits to solutions/lib are always picked up.
+    engine.set_module_resolver(FileModuleResolver::new_with_path(MODULE_DIR));
+
+    for (_, module) in native_modules() {
+        engine.register_global_module(module.into());
+    }
+    // Last, so that a `Line` can stand in for a string with all the functions above
+    let forwards = line::string_forwards(&engine);
+    engine.register_global_module(forwards.into());
 
     engine
-        .eval_file_with_scope(&mut scope, script_path.clone())
+}
+
+/// Load the parameters for `data_name` from the `params.json` of the day. The file maps the
+/// data file names to objects, days without one get an empty map.
+fn load_params(engine: &rhai::Engine, dir: &Path, data_name: &str) -> Result<rhai::Map> {
+    let path = dir.join("params.json");
+    if !path.exists() {
+        return Ok(rhai::Map::new());
+    }
+    let json = std::fs::read_to_string(&path)
+        .wrap_err_with(|| format!("Failed to read parameters from {path:?}"))?;
+    let mut all = engine
+        .parse_json(json, true)
         .map_err(|e| eyre!("{e}"))
+        .wrap_err_with(|| format!("Failed to parse parameters in {path:?}"))?;
+    match all.remove(data_name) {
+        Some(params) => params.try_cast().ok_or_else(|| {
+            eyre!("Expected the parameters for {data_name:?} in {path:?} to be an object")
+        }),
+        None => Ok(rhai::Map::new()),
+    }
+}
+
+/// A single input to run a script with, case files contain several of them
+struct Input {
+    name: Option<String>,
+    data: SharedAocData,
+    answers: [Option<String>; 2],
+    /// JSON object of parameters that override those in `params.json`
+    params: Option<String>,
+}
+
+/// Directory with the script and data files of `day`
+fn day_dir(day: u8) -> PathBuf {
+    Path::new(MODULE_DIR).join(format!("day-{day:02}"))
+}
+
+fn load_inputs(dir: &Path, data_name: &str) -> Result<Vec<Input>> {
+    let data_path = dir.join(data_name);
+    if !case_file::is_case_file(&data_path)? {
+        return Ok(vec![Input {
+            name: None,
+            data: aoc_data::AocData::load(data_path)?,
+            answers: [None, None],
+            params: None,
+        }]);
+    }
+
+    Ok(case_file::load(&data_path)?
+        .into_iter()
+        .map(|case| Input {
+            data: aoc_data::AocData::from_text(data_path.clone(), case.text, case.line_base),
+            name: Some(case.name),
+            answers: case.answers,
+            params: case.params,
+        })
+        .collect())
+}
+
+fn run_script(dir: &Path, data_name: &str, input: &Input) -> Result<[String; 2]> {
+    let script_path = dir.join("script.rhai");
+    let data = &input.data;
+
+    let engine = Shared::new(create_engine());
+    let _guard = dyn_iterator::EngineGuard::enter(engine.clone());
+    let mut scope = rhai::Scope::new();
+
+    let mut params = load_params(&engine, dir, data_name)?;
+    if let Some(json) = &input.params {
+        let case_params = engine
+            .parse_json(json, true)
+            .map_err(|e| eyre!("{e}"))
+            .wrap_err_with(|| format!("Failed to parse the parameters of case {:?}", input.name))?;
+        params.extend(case_params);
+    }
+
+    scope.push("data", data.clone());
+    scope.push("params", params);
+    scope.push("input_name", data_name.to_string());
+    scope.push("is_example", data_name.starts_with("test"));
+
+    engine
+        .eval_file_with_scope(&mut scope, script_path.clone())
+        .map_err(|e| {
+            let err = script_error(e, &script_path, Path::new(MODULE_DIR));
+            match data.borrow().location() {
+                Some(location) => eyre!("{err}\n  while processing {location}"),
+                None => err,
+            }
+        })
         .wrap_err_with(|| format!("Failed to run {script_path:?}"))
         .and_then(|ret: Array| {
             ensure!(
@@ -309,24 +536,128 @@ fn run_script(day: u8, data_name: &str) -> Result<[String; 2]> {
 }
 
 #[derive(clap::Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    day: u8,
-    #[clap(value_parser = ["test.dat", "user.dat"])]
-    data: String,
+    #[command(subcommand)]
+    cmd: Option<Cmd>,
+    #[arg(required = true)]
+    day: Option<u8>,
+    #[clap(value_parser = ["test.dat", "user.dat"], required = true)]
+    data: Option<String>,
+}
+
+#[derive(clap::Subcommand)]
+enum Cmd {
+    /// Compile all scripts and report syntax errors and calls to unknown functions
+    Check,
+    /// List the native functions available to the scripts
+    Functions {
+        /// Only list functions whose signature or module contains this text
+        pattern: Option<String>,
+        /// Write a definitions file for Rhai language servers to this path
+        #[arg(long, conflicts_with = "pattern")]
+        definitions: Option<PathBuf>,
+    },
+    /// Run every day that imports a module from solutions/lib, directly or through another
+    /// module, e.g. after changing it
+    Importers {
+        /// Module name as in `import`, like `lib/parsing`
+        module: String,
+        #[clap(value_parser = ["test.dat", "user.dat"])]
+        data: String,
+    },
 }
 
 fn main() -> Result<()> {
-    let Args { day, data } = clap::Parser::parse();
-    for (i, data) in run_script(day, &data)?.iter().enumerate() {
-        if data.contains('\n') {
-            println!("Part {}:", i + 1);
-            for l in data.lines() {
-                println!("  {l}");
+    let args: Args = clap::Parser::parse();
+    let (day, data) = match args.cmd {
+        Some(Cmd::Check) => {
+            let problems = check::check_scripts(create_engine(), Path::new(MODULE_DIR))?;
+            for problem in &problems {
+                println!("{problem}");
+            }
+            ensure!(problems.is_empty(), "Found {} problem(s)", problems.len());
+            println!("No problems found");
+            return Ok(());
+        }
+        Some(Cmd::Functions {
+            pattern,
+            definitions,
+        }) => {
+            return match definitions {
+                Some(path) => functions::write_definitions(native_modules(), &path),
+                None => {
+                    functions::list_functions(&native_modules(), pattern.as_deref());
+                    Ok(())
+                }
             }
+        }
+        Some(Cmd::Importers { module, data }) => {
+            let days = check::importers(&create_engine(), Path::new(MODULE_DIR), &module)?;
+            ensure!(!days.is_empty(), "No day imports {module:?}");
+            let mut failed = Vec::new();
+            for day in days {
+                println!("Day {day}:");
+                if let Err(e) = run_day(day, &data) {
+                    println!("FAILED: {e:#}");
+                    failed.push(day.to_string());
+                }
+            }
+            ensure!(failed.is_empty(), "Failed days: {}", failed.join(", "));
+            return Ok(());
+        }
+        None => (args.day.unwrap(), args.data.unwrap()),
+    };
+    run_day(day, &data)
+}
+
+fn run_day(day: u8, data: &str) -> Result<()> {
+    let dir = day_dir(day);
+    let inputs = load_inputs(&dir, data)?;
+    let mut failed = 0;
+    let mut errors = 0;
+    for input in &inputs {
+        let indent = if let Some(name) = &input.name {
+            println!("Case {name}:");
+            "  "
         } else {
-            println!("Part {}: {data}", i + 1);
+            ""
+        };
+        let res = match run_script(&dir, data, input) {
+            Ok(res) => res,
+            // A failing case is reported with the others, the remaining cases still run
+            Err(e) if input.name.is_some() => {
+                errors += 1;
+                for l in format!("FAILED: {e:#}").lines() {
+                    println!("{indent}{l}");
+                }
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        for (i, (got, want)) in res.iter().zip(&input.answers).enumerate() {
+            let status = match want {
+                Some(want) if want == got => " (ok)".to_string(),
+                Some(want) => {
+                    failed += 1;
+                    format!(" (FAILED, expected {want})")
+                }
+                None => String::new(),
+            };
+            if got.contains('\n') {
+                println!("{indent}Part {}:{status}", i + 1);
+                for l in got.lines() {
+                    println!("{indent}  {l}");
+                }
+            } else {
+                println!("{indent}Part {}: {got}{status}", i + 1);
+            }
         }
     }
+    ensure!(
+        errors == 0 && failed == 0,
+        "{errors} case(s) failed to run and {failed} answer(s) did not match the cases"
+    );
     Ok(())
 }
 
@@ -334,6 +665,7 @@ fn main() -> Result<()> {
 mod tests {
     use super::*;
 
+    #[allow(clippy::needless_return)]
     fn check_result(got: &str, want: &str, day: u8, part: &str, data: &str) {
         if got == want {
             return;
@@ -351,16 +683,184 @@ mod tests {
 
     fn run_test(day: u8, data: &str, part1: &str, part2: &str) -> Result<()> {
         println!("\n\nRunning script with {data}");
-        let res = run_script(day, data)?;
+        let dir = day_dir(day);
+        let inputs = load_inputs(&dir, data)?;
+        ensure!(inputs.len() == 1, "Expected {data} to hold a single input");
+        let res = run_script(&dir, data, &inputs[0])?;
         check_result(&res[0], part1, day, "part1", data);
         check_result(&res[1], part2, day, "part2", data);
         Ok(())
     }
 
+    fn run_case_test(day: u8, data: &str) -> Result<()> {
+        let dir = day_dir(day);
+        let inputs = load_inputs(&dir, data)?;
+        ensure!(
+            inputs.iter().all(|input| input.name.is_some()),
+            "Expected {data} to be a case file"
+        );
+        // Every case runs, the failures are reported together at the end
+        let mut failures = Vec::new();
+        for input in &inputs {
+            let name = format!("{data} case {}", input.name.as_ref().unwrap());
+            println!("\n\nRunning script with {name}");
+            let res = match run_script(&dir, data, input) {
+                Ok(res) => res,
+                Err(e) => {
+                    println!("{e:#}");
+                    failures.push(name);
+                    continue;
+                }
+            };
+            let answers = &input.answers;
+            let checked = std::panic::catch_unwind(|| {
+                for (i, (got, want)) in res.iter().zip(answers).enumerate() {
+                    if let Some(want) = want {
+                        check_result(got, want, day, &format!("part{}", i + 1), &name);
+                    }
+                }
+            });
+            if checked.is_err() {
+                failures.push(name);
+            }
+        }
+        ensure!(failures.is_empty(), "Failed: {}", failures.join(", "));
+        Ok(())
+    }
+
+    /// Scripts get the parameters of their data file, which cases can override, together
+    /// with the name of the data file
+    #[test]
+    fn params() -> Result<()> {
+        let dir = Path::new("tests/fixtures/params");
+        for (data, want) in [
+            ("user.dat", ["user.dat false", r#"{"row":2000000}"#]),
+            ("other.dat", ["other.dat false", "{}"]),
+        ] {
+            let inputs = load_inputs(dir, data)?;
+            assert_eq!(run_script(dir, data, &inputs[0])?, want);
+        }
+        let cases = load_inputs(dir, "test.dat")?;
+        assert_eq!(cases.len(), 2);
+        for case in &cases {
+            let res = run_script(dir, "test.dat", case)?;
+            let want = case.answers.clone().map(Option::unwrap);
+            assert_eq!(res, want, "case {:?}", case.name);
+        }
+        Ok(())
+    }
+
+    /// The lines of a case are numbered by where they are in the case file
+    #[test]
+    fn case_lines_count_from_the_start_of_the_file() -> Result<()> {
+        let inputs = load_inputs(&day_dir(6), "test.dat")?;
+        let engine = create_engine();
+        for (input, want) in inputs.iter().zip([4, 8, 12]) {
+            let mut scope = rhai::Scope::new();
+            scope.push("data", input.data.clone());
+            let script = "[data.numbered_lines().next().line_no, data.line_no]";
+            let got: Array = engine
+                .eval_with_scope(&mut scope, script)
+                .map_err(|e| eyre!("{e}"))?;
+            let got: Vec<INT> = got.into_iter().map(|v| v.as_int().unwrap()).collect();
+            assert_eq!(got, [want, want]);
+        }
+        Ok(())
+    }
+
+    /// Days reached through a chain of imports are found as well
+    #[test]
+    fn importers() -> Result<()> {
+        let engine = create_engine();
+        let dir = Path::new(MODULE_DIR);
+        assert_eq!(check::importers(&engine, dir, "lib/parsing")?, [11]);
+        assert!(check::importers(&engine, dir, "lib/unused")?.is_empty());
+
+        let dir = Path::new("tests/fixtures/importers");
+        assert_eq!(check::importers(&engine, dir, "lib/base")?, [1, 2]);
+        assert_eq!(check::importers(&engine, dir, "lib/derived")?, [2]);
+        Ok(())
+    }
+
+    #[test]
+    fn check_scripts() -> Result<()> {
+        let problems = check::check_scripts(create_engine(), Path::new(MODULE_DIR))?;
+        assert!(problems.is_empty(), "{}", problems.join("\n"));
+        Ok(())
+    }
+
+    /// Each kind of problem is found where it is
+    #[test]
+    fn check_finds_problems() -> Result<()> {
+        let dir = "tests/fixtures/check";
+        let problems = check::check_scripts(create_engine(), Path::new(dir))?;
+        assert_eq!(
+            problems,
+            [
+                format!("{dir}/day-01/script.rhai:3:14: Call to unknown function `parse_number`"),
+                format!(
+                    "{dir}/day-02/script.rhai:5:14: `todo` called with 1 argument(s), but it takes [0]"
+                ),
+                format!("{dir}/day-03/script.rhai:4:17: Unknown module `helper` in call to `double`"),
+            ]
+        );
+        Ok(())
+    }
+
+    /// Every `DynIterator` in a native function signature must be in the item type registry,
+    /// or `for` loops over it fail at runtime
+    #[test]
+    fn iterators_are_iterable() {
+        let registered = dyn_iterator::registered_iterators();
+        let modules = native_modules();
+        for (module_name, module) in &modules {
+            for sig in module.gen_fn_signatures() {
+                let mut rest = sig.as_str();
+                while let Some(start) = rest.find("DynIterator<") {
+                    rest = &rest[start..];
+                    let mut depth = 0;
+                    let end = rest
+                        .char_indices()
+                        .find_map(|(i, c)| {
+                            match c {
+                                '<' => depth += 1,
+                                '>' if depth == 1 => return Some(i + 1),
+                                '>' => depth -= 1,
+                                _ => {}
+                            }
+                            None
+                        })
+                        .unwrap();
+                    let typ = &rest[..end];
+                    assert!(
+                        registered.iter().any(|(name, _)| name == typ),
+                        "{typ} of {module_name}: {sig} is missing in for_item_types!"
+                    );
+                    rest = &rest[end..];
+                }
+            }
+        }
+        for (typ, id) in registered {
+            assert!(
+                modules.iter().any(|(_, module)| module.contains_iter(id)),
+                "{typ} is not iterable"
+            );
+        }
+    }
+
+    macro_rules! impl_test_body {
+        ($day:ident, $data:expr, cases) => {
+            super::run_case_test($day, $data)
+        };
+        ($day:ident, $data:expr, ($p1:expr, $p2:expr)) => {
+            super::run_test($day, $data, $p1, $p2)
+        };
+    }
+
     macro_rules! impl_tests {
         ($(
             $day:ident = (
-                $($name:ident=($p1:expr, $p2:expr),)+
+                $($name:ident=$want:tt,)+
             )
         ,)+) => {$(
             mod $day {$(
@@ -368,7 +868,7 @@ mod tests {
                 fn $name() -> super::Result<()> {
                     let day = stringify!($day).split_once('_').unwrap().1.parse()?;
                     let data = concat!(stringify!($name), ".dat");
-                    super::run_test(day, data, $p1, $p2)
+                    impl_test_body!(day, data, $want)
                 }
             )+}
         )+}
@@ -380,10 +880,7 @@ mod tests {
         day_03 = (test = ("157", "70"), user = ("8176", "2689"),),
         day_04 = (test = ("2", "4"), user = ("562", "924"),),
         day_05 = (test = ("CMZ", "MCD"), user = ("QNNTGTPFN", "GGNPJBTTR"),),
-        day_06 = (
-            test = ("[7, 5, 6, 10, 11]", "[19, 23, 23, 29, 26]"),
-            user = ("[1855]", "[3256]"),
-        ),
+        day_06 = (test = cases, user = ("1855", "3256"),),
         day_07 = (test = ("95437", "24933642"), user = ("1427048", "2940614"),),
         day_08 = (test = ("21", "8"), user = ("1708", "504000"),),
         day_09 = (
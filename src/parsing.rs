@@ -0,0 +1,155 @@
+use crate::*;
+
+pub use self::parsing::*;
+
+/// Parse a nested list literal like `[1,[2,[]],-3]` into arrays of integers. The error
+/// names the 1-based column of the offending character.
+fn parse_nested_list(text: &str) -> Result<Dynamic, String> {
+    fn skip_ws(bytes: &[u8], pos: &mut usize) {
+        while bytes.get(*pos).is_some_and(u8::is_ascii_whitespace) {
+            *pos += 1;
+        }
+    }
+
+    fn unexpected(bytes: &[u8], pos: usize, expected: &str) -> String {
+        match bytes.get(pos) {
+            Some(&b) => format!(
+                "Expected {expected} at column {}, got {:?}",
+                pos + 1,
+                char::from(b)
+            ),
+            None => format!("Expected {expected} at column {}, got the end", pos + 1),
+        }
+    }
+
+    fn value(text: &str, pos: &mut usize) -> Result<Dynamic, String> {
+        let bytes = text.as_bytes();
+        skip_ws(bytes, pos);
+        match bytes.get(*pos) {
+            Some(b'[') => {
+                *pos += 1;
+                let mut ret = Array::new();
+                skip_ws(bytes, pos);
+                if bytes.get(*pos) == Some(&b']') {
+                    *pos += 1;
+                    return Ok(ret.into());
+                }
+                loop {
+                    ret.push(value(text, pos)?);
+                    skip_ws(bytes, pos);
+                    match bytes.get(*pos) {
+                        Some(b',') => *pos += 1,
+                        Some(b']') => {
+                            *pos += 1;
+                            return Ok(ret.into());
+                        }
+                        _ => return Err(unexpected(bytes, *pos, "',' or ']'")),
+                    }
+                }
+            }
+            Some(b'-' | b'0'..=b'9') => {
+                let start = *pos;
+                *pos += 1;
+                while bytes.get(*pos).is_some_and(u8::is_ascii_digit) {
+                    *pos += 1;
+                }
+                let num = &text[start..*pos];
+                num.parse::<INT>()
+                    .map(Dynamic::from)
+                    .map_err(|e| format!("Failed to parse {num} at column {}: {e}", start + 1))
+            }
+            _ => Err(unexpected(bytes, *pos, "'[' or an integer")),
+        }
+    }
+
+    let mut pos = 0;
+    let ret = value(text, &mut pos)?;
+    skip_ws(text.as_bytes(), &mut pos);
+    if pos < text.len() {
+        return Err(unexpected(text.as_bytes(), pos, "the end"));
+    }
+    Ok(ret)
+}
+
+/// Convert parsed JSON to Rhai values, integers stay `INT` when they fit
+fn json_to_dynamic(value: serde_json::Value) -> Dynamic {
+    use serde_json::Value;
+    match value {
+        Value::Null => Dynamic::UNIT,
+        Value::Bool(v) => v.into(),
+        Value::Number(v) => match v.as_i64() {
+            Some(v) => v.into(),
+            None => v.as_f64().map_or(Dynamic::UNIT, Dynamic::from),
+        },
+        Value::String(v) => v.into(),
+        Value::Array(v) => v.into_iter().map(json_to_dynamic).collect::<Array>().into(),
+        Value::Object(v) => v
+            .into_iter()
+            .map(|(k, v)| (k.into(), json_to_dynamic(v)))
+            .collect::<rhai::Map>()
+            .into(),
+    }
+}
+
+#[export_module]
+mod parsing {
+    /// Parse a nested list of integers like `[1,[2,[]]]` without evaluating it as script
+    #[rhai_fn(return_raw)]
+    pub fn parse_nested(ctx: NativeCallContext, s: &str) -> RhaiRes<Dynamic> {
+        super::parse_nested_list(s).map_err(|e| runtime_error(&ctx, e))
+    }
+
+    /// Parse JSON into arrays, maps, strings, integers, floats, booleans and `()`
+    #[rhai_fn(return_raw)]
+    pub fn parse_json(ctx: NativeCallContext, s: &str) -> RhaiRes<Dynamic> {
+        serde_json::from_str(s)
+            .map(super::json_to_dynamic)
+            .map_err(|e| runtime_error(&ctx, format!("Invalid JSON: {e}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nested(text: &str) -> Result<String, String> {
+        parse_nested_list(text).map(|v| format!("{v:?}"))
+    }
+
+    #[test]
+    fn nested_lists() {
+        assert_eq!(nested("[1,[2,[]],-3]"), Ok("[1, [2, []], -3]".into()));
+        assert_eq!(nested(" [ 1 , [ ] ] "), Ok("[1, []]".into()));
+        assert_eq!(nested("[]"), Ok("[]".into()));
+        assert_eq!(nested("42"), Ok("42".into()));
+    }
+
+    #[test]
+    fn nested_list_errors() {
+        for (text, err) in [
+            ("", "Expected '[' or an integer at column 1, got the end"),
+            ("[1,2", "Expected ',' or ']' at column 5, got the end"),
+            ("[1;2]", "Expected ',' or ']' at column 3, got ';'"),
+            ("[1,]", "Expected '[' or an integer at column 4, got ']'"),
+            ("[[]]]", "Expected the end at column 5, got ']'"),
+            ("[a]", "Expected '[' or an integer at column 2, got 'a'"),
+            ("[1,-]", "Failed to parse - at column 4: invalid digit found in string"),
+            (
+                "[99999999999999999999]",
+                "Failed to parse 99999999999999999999 at column 2: number too large to fit in target type",
+            ),
+        ] {
+            assert_eq!(nested(text), Err(err.into()), "{text}");
+        }
+    }
+
+    #[test]
+    fn json_values() {
+        let json = r#"{"a": [1, -2.5, null, true], "b": {"c": "d"}, "e": 18446744073709551615}"#;
+        let value = json_to_dynamic(serde_json::from_str(json).unwrap());
+        assert_eq!(
+            format!("{value:?}"),
+            r#"#{"a": [1, -2.5, (), true], "b": #{"c": "d"}, "e": 1.8446744073709552e19}"#
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{line::Line, *};
+use regex::Regex;
+
+pub use self::regex_extras::*;
+
+thread_local! {
+    static REGEX_CACHE: RefCell<HashMap<String, Regex>> = RefCell::new(HashMap::new());
+}
+
+/// Compile `pattern` only the first time it is seen, scripts tend to pass the same literal
+/// patterns over and over inside their loops.
+fn cached_regex(ctx: &NativeCallContext, pattern: &str) -> RhaiRes<Regex> {
+    REGEX_CACHE.with(|cache| {
+        if let Some(re) = cache.borrow().get(pattern) {
+            return Ok(re.clone());
+        }
+        let re = Regex::new(pattern).map_err(|e| runtime_error(ctx, e.to_string()))?;
+        cache.borrow_mut().insert(pattern.into(), re.clone());
+        Ok(re)
+    })
+}
+
+/// Map of the groups captured by `re` in `s`, named groups use their name and the rest
+/// their index. Returns `()` when there is no match.
+fn regex_captures(re: &Regex, s: &str) -> Dynamic {
+    let Some(caps) = re.captures(s) else {
+        return Dynamic::UNIT;
+    };
+    let mut ret = rhai::Map::new();
+    for (i, name) in re.capture_names().enumerate() {
+        let key = name.map_or_else(|| i.to_string(), str::to_string);
+        let value = caps
+            .get(i)
+            .map_or(Dynamic::UNIT, |m| ImmutableString::from(m.as_str()).into());
+        ret.insert(key.into(), value);
+    }
+    ret.into()
+}
+
+#[export_module]
+mod regex_extras {
+    /// Compile a regular expression once to use it with `is_match`, `captures`,
+    /// `find_all` and `replace`
+    #[rhai_fn(return_raw)]
+    pub fn regex(ctx: NativeCallContext, pattern: &str) -> RhaiRes<Regex> {
+        super::cached_regex(&ctx, pattern)
+    }
+
+    #[rhai_fn(name = "is_match")]
+    pub fn is_match_regex(s: &str, re: Regex) -> bool {
+        re.is_match(s)
+    }
+
+    #[rhai_fn(name = "is_match", return_raw)]
+    pub fn is_match_str(ctx: NativeCallContext, s: &str, pattern: &str) -> RhaiRes<bool> {
+        Ok(super::cached_regex(&ctx, pattern)?.is_match(s))
+    }
+
+    #[rhai_fn(name = "captures")]
+    pub fn captures_regex(s: &str, re: Regex) -> Dynamic {
+        super::regex_captures(&re, s)
+    }
+
+    #[rhai_fn(name = "captures", return_raw)]
+    pub fn captures_str(ctx: NativeCallContext, s: &str, pattern: &str) -> RhaiRes<Dynamic> {
+        Ok(super::regex_captures(
+            &super::cached_regex(&ctx, pattern)?,
+            s,
+        ))
+    }
+
+    #[rhai_fn(name = "find_all")]
+    pub fn find_all_regex(s: &str, re: Regex) -> Array {
+        re.find_iter(s)
+            .map(|m| ImmutableString::from(m.as_str()).into())
+            .collect()
+    }
+
+    #[rhai_fn(name = "find_all", return_raw)]
+    pub fn find_all_str(ctx: NativeCallContext, s: &str, pattern: &str) -> RhaiRes<Array> {
+        Ok(find_all_regex(s, super::cached_regex(&ctx, pattern)?))
+    }
+
+    /// Replace all matches in place, `$1` and `$name` in `replacement` refer to the groups.
+    /// Only takes a compiled `regex()` since `replace(string, string)` is the plain text
+    /// replace of Rhai.
+    #[rhai_fn(name = "replace")]
+    pub fn replace_regex(s: &mut ImmutableString, re: Regex, replacement: &str) {
+        if let std::borrow::Cow::Owned(v) = re.replace_all(s, replacement) {
+            *s = v.into();
+        }
+    }
+
+    /// Capture the groups of `re` in each line, see `captures` for strings. Stops with an
+    /// error on the first line that does not match.
+    #[rhai_fn(name = "captures")]
+    pub fn lines_captures_regex(
+        ctx: NativeCallContext,
+        lines: DynIterator<ImmutableString>,
+        re: Regex,
+    ) -> DynIterator<Dynamic> {
+        let pos = ctx.position();
+        // The reader adds the number of the line it is processing to the error
+        lines.try_map(move |line| {
+            let caps = super::regex_captures(&re, &line);
+            if caps.is_unit() {
+                Err(Box::new(EvalAltResult::ErrorRuntime(
+                    format!("{:?} does not match {line:?}", re.as_str()).into(),
+                    pos,
+                )))
+            } else {
+                Ok(caps)
+            }
+        })
+    }
+
+    #[rhai_fn(name = "captures", return_raw)]
+    pub fn lines_captures_str(
+        ctx: NativeCallContext,
+        lines: DynIterator<ImmutableString>,
+        pattern: &str,
+    ) -> RhaiRes<DynIterator<Dynamic>> {
+        let re = super::cached_regex(&ctx, pattern)?;
+        Ok(lines_captures_regex(ctx, lines, re))
+    }
+
+    /// `captures` for numbered lines, errors tell where the line is in the file
+    #[rhai_fn(name = "captures")]
+    pub fn numbered_lines_captures_regex(
+        ctx: NativeCallContext,
+        lines: DynIterator<Line>,
+        re: Regex,
+    ) -> DynIterator<Dynamic> {
+        let pos = ctx.position();
+        lines.try_map(move |line| {
+            let caps = super::regex_captures(&re, line.text());
+            if caps.is_unit() {
+                Err(Box::new(EvalAltResult::ErrorRuntime(
+                    format!(
+                        "{:?} does not match {:?} on {}",
+                        re.as_str(),
+                        line.text(),
+                        line.location()
+                    )
+                    .into(),
+                    pos,
+                )))
+            } else {
+                Ok(caps)
+            }
+        })
+    }
+
+    #[rhai_fn(name = "captures", return_raw)]
+    pub fn numbered_lines_captures_str(
+        ctx: NativeCallContext,
+        lines: DynIterator<Line>,
+        pattern: &str,
+    ) -> RhaiRes<DynIterator<Dynamic>> {
+        let re = super::cached_regex(&ctx, pattern)?;
+        Ok(numbered_lines_captures_regex(ctx, lines, re))
+    }
+}
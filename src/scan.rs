@@ -0,0 +1,226 @@
+use crate::{line::Line, *};
+
+pub use self::scan::*;
+
+#[export_module]
+mod scan {
+    use rhai::Map;
+
+    #[derive(Clone, Copy, Debug)]
+    enum FieldKind {
+        Int,
+        Word,
+        Char,
+        Rest,
+    }
+
+    enum Part {
+        Literal(String),
+        Field(Option<ImmutableString>, FieldKind),
+    }
+
+    /// A template like `move {int} from {from:int}`, the fields are returned as an array
+    /// or as a map when all of them are named.
+    pub struct ScanPattern {
+        parts: Vec<Part>,
+        named: bool,
+    }
+
+    impl ScanPattern {
+        fn parse(ctx: &NativeCallContext, pattern: &str) -> RhaiRes<Self> {
+            let err =
+                |msg: &str| runtime_error(ctx, format!("Invalid scan pattern {pattern:?}: {msg}"));
+            let mut parts = Vec::new();
+            let mut literal = String::new();
+            let mut chars = pattern.chars().peekable();
+
+            while let Some(c) = chars.next() {
+                match c {
+                    '{' if chars.peek() == Some(&'{') => {
+                        chars.next();
+                        literal.push('{');
+                    }
+                    '}' if chars.peek() == Some(&'}') => {
+                        chars.next();
+                        literal.push('}');
+                    }
+                    '}' => return Err(err("unmatched '}'")),
+                    '{' => {
+                        let mut field = String::new();
+                        loop {
+                            match chars.next() {
+                                Some('}') => break,
+                                Some(c) => field.push(c),
+                                None => return Err(err("unterminated '{'")),
+                            }
+                        }
+                        let (name, kind) = match field.split_once(':') {
+                            Some((name, kind)) => (Some(name.trim().into()), kind.trim()),
+                            None => (None, field.trim()),
+                        };
+                        let kind = match kind {
+                            "int" => FieldKind::Int,
+                            "word" => FieldKind::Word,
+                            "char" => FieldKind::Char,
+                            "rest" => FieldKind::Rest,
+                            _ => return Err(err(&format!("unknown field type {kind:?}"))),
+                        };
+                        if !literal.is_empty() {
+                            parts.push(Part::Literal(std::mem::take(&mut literal)));
+                        }
+                        parts.push(Part::Field(name, kind));
+                    }
+                    c => literal.push(c),
+                }
+            }
+            if !literal.is_empty() {
+                parts.push(Part::Literal(literal));
+            }
+
+            let mut names = parts.iter().filter_map(|part| match part {
+                Part::Field(name, _) => Some(name.is_some()),
+                Part::Literal(_) => None,
+            });
+            let named = names.next().unwrap_or(false);
+            if names.any(|v| v != named) {
+                return Err(err("either all or none of the fields must be named"));
+            }
+            Ok(Self { parts, named })
+        }
+
+        /// Match `line` against the pattern, returns a description of what was expected
+        /// and the column where it failed on mismatch.
+        fn scan(&self, line: &str) -> Result<Dynamic, (String, usize)> {
+            let mut rest = line;
+            let mut values = Vec::new();
+            let column = |rest: &str| line.len() - rest.len() + 1;
+
+            for (i, part) in self.parts.iter().enumerate() {
+                match part {
+                    Part::Literal(lit) => {
+                        rest = rest
+                            .strip_prefix(lit.as_str())
+                            .ok_or_else(|| (format!("{lit:?}"), column(rest)))?;
+                    }
+                    Part::Field(name, kind) => {
+                        let end = match kind {
+                            FieldKind::Int => {
+                                let digits = rest.strip_prefix(['-', '+']).unwrap_or(rest);
+                                let len = digits.len()
+                                    - digits
+                                        .trim_start_matches(|c: char| c.is_ascii_digit())
+                                        .len();
+                                (len > 0).then(|| rest.len() - digits.len() + len)
+                            }
+                            FieldKind::Word => {
+                                let stop = match self.parts.get(i + 1) {
+                                    Some(Part::Literal(lit)) => lit.chars().next(),
+                                    _ => None,
+                                };
+                                let end = rest
+                                    .find(|c: char| c.is_whitespace() || Some(c) == stop)
+                                    .unwrap_or(rest.len());
+                                (end > 0).then_some(end)
+                            }
+                            FieldKind::Char => rest.chars().next().map(char::len_utf8),
+                            FieldKind::Rest => Some(rest.len()),
+                        };
+                        let end =
+                            end.ok_or_else(|| (format!("{kind:?}").to_lowercase(), column(rest)))?;
+                        let (value, tail) = rest.split_at(end);
+                        let value = match kind {
+                            FieldKind::Int => Dynamic::from(
+                                value
+                                    .parse::<INT>()
+                                    .map_err(|e| (format!("int ({e})"), column(rest)))?,
+                            ),
+                            FieldKind::Char => Dynamic::from(value.chars().next().unwrap()),
+                            FieldKind::Word | FieldKind::Rest => {
+                                Dynamic::from(ImmutableString::from(value))
+                            }
+                        };
+                        values.push((name, value));
+                        rest = tail;
+                    }
+                }
+            }
+
+            if !rest.is_empty() {
+                return Err(("end of line".into(), column(rest)));
+            }
+            Ok(if self.named {
+                values
+                    .into_iter()
+                    .map(|(name, value)| (name.as_ref().unwrap().as_str().into(), value))
+                    .collect::<Map>()
+                    .into()
+            } else {
+                values
+                    .into_iter()
+                    .map(|(_, value)| value)
+                    .collect::<Array>()
+                    .into()
+            })
+        }
+    }
+
+    /// Parse `line` with a template like `move {int} from {int} to {int}`. Fields are
+    /// `{int}`, `{word}`, `{char}` and `{rest}`, or `{name:int}` etc. to return a map.
+    #[rhai_fn(return_raw)]
+    pub fn scan(ctx: NativeCallContext, line: &str, pattern: &str) -> RhaiRes<Dynamic> {
+        ScanPattern::parse(&ctx, pattern)?
+            .scan(line)
+            .map_err(|(expected, column)| {
+                runtime_error(
+                    &ctx,
+                    format!("Expected {expected} at column {column} of {line:?} for {pattern:?}"),
+                )
+            })
+    }
+
+    /// Parse each line with the template, stops with an error on the first line that
+    /// does not match.
+    #[rhai_fn(name = "scan", return_raw)]
+    pub fn scan_lines(
+        ctx: NativeCallContext,
+        lines: DynIterator<ImmutableString>,
+        pattern: ImmutableString,
+    ) -> RhaiRes<DynIterator<Dynamic>> {
+        let scanner = ScanPattern::parse(&ctx, &pattern)?;
+        let pos = ctx.position();
+        // The reader adds the number of the line it is processing to the error
+        Ok(lines.try_map(move |line| {
+            scanner.scan(&line).map_err(|(expected, column)| {
+                Box::new(EvalAltResult::ErrorRuntime(
+                    format!("Expected {expected} at column {column} of {line:?} for {pattern:?}")
+                        .into(),
+                    pos,
+                ))
+            })
+        }))
+    }
+
+    /// `scan` for numbered lines, errors tell where the line is in the file
+    #[rhai_fn(name = "scan", return_raw)]
+    pub fn scan_numbered_lines(
+        ctx: NativeCallContext,
+        lines: DynIterator<Line>,
+        pattern: ImmutableString,
+    ) -> RhaiRes<DynIterator<Dynamic>> {
+        let scanner = ScanPattern::parse(&ctx, &pattern)?;
+        let pos = ctx.position();
+        Ok(lines.try_map(move |line| {
+            scanner.scan(line.text()).map_err(|(expected, column)| {
+                Box::new(EvalAltResult::ErrorRuntime(
+                    format!(
+                        "Expected {expected} at column {column} of {:?} for {pattern:?} on {}",
+                        line.text(),
+                        line.location()
+                    )
+                    .into(),
+                    pos,
+                ))
+            })
+        }))
+    }
+}
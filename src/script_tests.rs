@@ -0,0 +1,980 @@
+//! Script snippets run against small inputs, for the native functions the day scripts do
+//! not cover on their own
+
+use crate::*;
+
+/// Evaluate `script` with `data` reading `text` from `test.dat`. Gives the debug form of the
+/// result or the error message, which tells the line being processed like `run_script`.
+fn eval(text: &str, script: &str) -> std::result::Result<String, String> {
+    let data = aoc_data::AocData::from_text("test.dat".into(), text.to_string(), 0);
+    eval_data(data, script)
+}
+
+/// `eval` with `data` reading a fixture file
+fn eval_file(path: &str, script: &str) -> std::result::Result<String, String> {
+    let data = aoc_data::AocData::load(path.into()).map_err(|e| e.to_string())?;
+    eval_data(data, script)
+}
+
+fn eval_data(data: SharedAocData, script: &str) -> std::result::Result<String, String> {
+    let engine = Shared::new(create_engine());
+    let _guard = dyn_iterator::EngineGuard::enter(engine.clone());
+    let mut scope = rhai::Scope::new();
+    scope.push("data", data.clone());
+    engine
+        .eval_with_scope::<Dynamic>(&mut scope, script)
+        .map(|v| format!("{v:?}"))
+        .map_err(|e| match data.borrow().location() {
+            Some(location) => format!("{e}\n  while processing {location}"),
+            None => e.to_string(),
+        })
+}
+
+/// `eval` for scripts that must succeed
+fn eval_ok(text: &str, script: &str) -> String {
+    eval(text, script).unwrap_or_else(|e| panic!("{script} failed: {e}"))
+}
+
+/// `eval` for scripts that must fail, gives the error message
+fn eval_err(text: &str, script: &str) -> String {
+    match eval(text, script) {
+        Ok(v) => panic!("{script} should have failed, but gave {v}"),
+        Err(e) => e,
+    }
+}
+
+#[test]
+fn dedup_compares_values() {
+    let lines = "1\n1\n2\n1\n";
+    assert_eq!(
+        eval_ok(lines, "data.lines().dedup().collect()"),
+        r#"["1", "2", "1"]"#
+    );
+    assert_eq!(
+        eval_ok(
+            lines,
+            "data.lines().map(|l| parse_int(l)).dedup().collect()"
+        ),
+        "[1, 2, 1]"
+    );
+    let pairs = "1,2\n1,2\n3,4\n";
+    assert_eq!(eval_ok(pairs, "data.lines().ints().dedup().count()"), "2");
+    assert_eq!(
+        eval_ok(
+            pairs,
+            "data.lines().ints().map(|v| tuple(v[0], v[1])).dedup().count()"
+        ),
+        "2"
+    );
+    assert_eq!(
+        eval_ok(
+            pairs,
+            "data.lines().map(|l| [l.len(), [l]]).dedup().count()"
+        ),
+        "2"
+    );
+    // A number and a string with the same string form are different values
+    assert_eq!(
+        eval_ok(
+            lines,
+            r#"data.lines().map(|l| if l == "2" { 1 } else { l }).dedup().count()"#
+        ),
+        "3"
+    );
+    assert!(
+        eval_err(lines, "data.lines().map(|l| #{l: l}).dedup().count()")
+            .contains("a value that can be compared")
+    );
+}
+
+#[test]
+fn run_lengths_compare_values() {
+    assert_eq!(
+        eval_ok(
+            "1\n1\n2\n1\n",
+            "data.lines().map(|l| parse_int(l)).run_lengths().collect()"
+        ),
+        "[[1, 2], [2, 1], [1, 1]]"
+    );
+    assert_eq!(
+        eval_ok("a\na\nb\n", "data.lines().run_lengths().collect()"),
+        r#"[["a", 2], ["b", 1]]"#
+    );
+    assert_eq!(
+        eval_ok(
+            "1,2\n3,4\n3,4\n",
+            "data.lines().ints().map(|v| tuple(v[0], v[1])).run_lengths().map(|r| r[1]).collect()"
+        ),
+        "[1, 2]"
+    );
+}
+
+#[test]
+fn chunk_by_compares_keys() {
+    let lines = "1\n3\n2\n4\n5\n";
+    assert_eq!(
+        eval_ok(
+            lines,
+            "data.lines().map(|l| parse_int(l)).chunk_by(|v| v % 2).collect()"
+        ),
+        "[[1, 3], [2, 4], [5]]"
+    );
+    assert_eq!(
+        eval_ok(
+            "ab\nac\nb\n",
+            "data.lines().chunk_by(|s| s.sub_string(0, 1)).collect()"
+        ),
+        r#"[["ab", "ac"], ["b"]]"#
+    );
+    assert_eq!(
+        eval_ok(
+            "1\n2\n11\n12\n",
+            "data.lines().chunk_by(|l| tuple(parse_int(l) / 10, 0)).map(|c| c.len()).collect()"
+        ),
+        "[2, 2]"
+    );
+    assert!(
+        eval_err(lines, "data.lines().chunk_by(|l| #{l: l}).collect()")
+            .contains("a value that can be compared")
+    );
+}
+
+#[test]
+fn collect_ints_and_sets() {
+    assert_eq!(
+        eval_ok(
+            "1\n2\n",
+            "data.lines().map(|l| parse_int(l)).collect_ints().to_debug()"
+        ),
+        r#""[1, 2]""#
+    );
+    assert!(eval_err("", r#"[1, "2"].collect_ints()"#).contains("integer"));
+    assert_eq!(eval_ok("", "[3, 1, 3].collect_set().to_array()"), "[1, 3]");
+    assert_eq!(
+        eval_ok("b\na\nb\n", "data.lines().collect_set().to_array()"),
+        r#"["a", "b"]"#
+    );
+    assert_eq!(
+        eval_ok(
+            "",
+            "[tuple(1, 2), tuple(1, 2), tuple(0, 5)].collect_set().to_array().map(|t| t.to_debug())"
+        ),
+        r#"["(0, 5)", "(1, 2)"]"#
+    );
+    assert!(eval_err("", "[1.5].collect_set()").contains("integers, strings or (INT, INT)"));
+}
+
+#[test]
+fn map_keys_tell_values_apart() {
+    assert_eq!(
+        eval_ok("", "[tuple(1, 2), tuple(3, 4), tuple(1, 2)].counts()"),
+        r#"#{"(1, 2)": 2, "(3, 4)": 1}"#
+    );
+    assert_eq!(
+        eval_ok("a\nb\na\n", "data.lines().counts()"),
+        r#"#{"a": 2, "b": 1}"#
+    );
+    assert_eq!(
+        eval_ok("1,2\n1,2\n3,4\n", "data.lines().ints().counts()"),
+        r#"#{"[1, 2]": 2, "[3, 4]": 1}"#
+    );
+    assert_eq!(
+        eval_ok("", r#"[[1, "a"], [1, 'a'], [1, "a"]].counts()"#),
+        r#"#{"[1, \"a\"]": 2, "[1, 'a']": 1}"#
+    );
+    assert_eq!(
+        eval_ok("", "[1, 2, 3, 4].group_by(|v| tuple(v % 2, 0))"),
+        r#"#{"(0, 0)": [2, 4], "(1, 0)": [1, 3]}"#
+    );
+    assert_eq!(
+        eval_ok("ab\nac\nb\n", "data.lines().group_by(|l| l[0])"),
+        r#"#{"a": ["ab", "ac"], "b": ["b"]}"#
+    );
+    assert_eq!(
+        eval_ok(
+            "",
+            "[tuple(1, 2), tuple(3, 4)].collect_map(|t| t, |t| t[1])"
+        ),
+        r#"#{"(1, 2)": 2, "(3, 4)": 4}"#
+    );
+    assert_eq!(
+        eval_ok(
+            "a=1\nb=2\n",
+            r#"data.lines().collect_map(|l| l.split("=")[0], |l| l.ints()[0])"#
+        ),
+        r#"#{"a": 1, "b": 2}"#
+    );
+    // Top-level strings and chars are not quoted, other values with the same key join them
+    assert_eq!(eval_ok("", r#"[1, "1", '1'].counts()"#), r#"#{"1": 3}"#);
+    assert_eq!(
+        eval_ok("", r#"['a', "a"].group_by(|v| v)"#),
+        r#"#{"a": ['a', "a"]}"#
+    );
+    assert_eq!(
+        eval_ok("", r#"[[1], ["1"]].counts()"#),
+        r#"#{"[\"1\"]": 1, "[1]": 1}"#
+    );
+    for script in [
+        "[#{a: 1}].counts()",
+        "[1].group_by(|v| 1.5)",
+        "[1].collect_map(|v| #{}, |v| v)",
+    ] {
+        assert!(
+            eval_err("", script)
+                .contains("a string, integer, char, bool, (INT, INT), array or IntArray key"),
+            "{script}"
+        );
+    }
+}
+
+#[test]
+fn scan_fields() {
+    assert_eq!(
+        eval_ok(
+            "",
+            r#""move 3 from 1 to -2".scan("move {int} from {int} to {int}")"#
+        ),
+        "[3, 1, -2]"
+    );
+    assert_eq!(
+        eval_ok("", r#""cd a.b/c".scan("cd {word}")"#),
+        r#"["a.b/c"]"#
+    );
+    assert_eq!(
+        eval_ok("", r#""x=ab,y".scan("{char}={word},{rest}")"#),
+        r#"['x', "ab", "y"]"#
+    );
+    assert_eq!(
+        eval_ok("", r#""Valve AA has rate=0".scan("Valve {word} {rest}")"#),
+        r#"["AA", "has rate=0"]"#
+    );
+    assert_eq!(
+        eval_ok("", r#""a 1".scan("{name:word} {n: int}")"#),
+        r#"#{"n": 1, "name": "a"}"#
+    );
+    assert_eq!(eval_ok("", r#""{7}".scan("{{{int}}}")"#), "[7]");
+}
+
+#[test]
+fn scan_pattern_errors() {
+    for (pattern, msg) in [
+        ("move {int", "unterminated '{'"),
+        ("move }", "unmatched '}'"),
+        ("move {float}", "unknown field type \"float\""),
+        (
+            "{a:int} {int}",
+            "either all or none of the fields must be named",
+        ),
+    ] {
+        let err = eval_err("", &format!("{:?}.scan({pattern:?})", "move 3"));
+        assert!(err.contains(msg), "{pattern}: {err}");
+    }
+}
+
+#[test]
+fn scan_mismatches() {
+    let err = eval_err("", r#""move x".scan("move {int}")"#);
+    assert!(
+        err.contains(r#"Expected int at column 6 of "move x" for "move {int}""#),
+        "{err}"
+    );
+    let err = eval_err("", r#""move 3 now".scan("move {int}")"#);
+    assert!(err.contains("Expected end of line at column 7"), "{err}");
+    let err = eval_err("", r#""go 3".scan("move {int}")"#);
+    assert!(err.contains(r#"Expected "move " at column 1"#), "{err}");
+    let err = eval_err("", r#""n 99999999999999999999".scan("n {int}")"#);
+    assert!(err.contains("Expected int (number too large"), "{err}");
+}
+
+#[test]
+fn scan_errors_tell_the_line() {
+    let text = "move 1\nmove 2\nmove x\n";
+    // A line read from `data` is located by the reader
+    let err = eval_err(text, r#"data.lines().scan("move {int}").collect()"#);
+    assert!(err.contains("while processing test.dat:3"), "{err}");
+    let err = eval_err(
+        text,
+        r#"let it = data.lines(); it.next(); it.next(); it.next().scan("move {int}")"#,
+    );
+    assert!(err.contains("while processing test.dat:3"), "{err}");
+    // Numbered lines know where they are
+    let err = eval_err(
+        text,
+        r#"data.numbered_lines().scan("move {int}").collect()"#,
+    );
+    assert!(
+        err.contains(r#"of "move x" for "move {int}" on test.dat:3"#),
+        "{err}"
+    );
+    let err = eval_err(
+        text,
+        r#"let it = data.numbered_lines(); it.next(); it.next(); it.next().scan("move {int}")"#,
+    );
+    assert!(err.contains("scan for line test.dat:3"), "{err}");
+    assert_eq!(
+        eval_ok(
+            text,
+            r#"data.numbered_lines().take(2).scan("move {int}").collect()"#
+        ),
+        "[[1], [2]]"
+    );
+}
+
+#[test]
+fn regex_functions() {
+    assert_eq!(eval_ok("", r#""a1b22".is_match("\\d+")"#), "true");
+    assert_eq!(eval_ok("", r#""ab".is_match(regex("^\\d"))"#), "false");
+    assert_eq!(
+        eval_ok("", r#""x=1,y=22".find_all("\\d+")"#),
+        r#"["1", "22"]"#
+    );
+    assert_eq!(
+        eval_ok("", r#""x=1,y=22".find_all(regex("[a-z]"))"#),
+        r#"["x", "y"]"#
+    );
+    assert_eq!(
+        eval_ok("", r#""x=1".captures("(?<name>\\w)=(\\d)")"#),
+        r#"#{"0": "x=1", "2": "1", "name": "x"}"#
+    );
+    assert_eq!(
+        eval_ok("", r#""x=1".captures(regex("(\\w)=(\\d)?(y)?"))"#),
+        r#"#{"0": "x=1", "1": "x", "2": "1", "3": ()}"#
+    );
+    assert_eq!(eval_ok("", r#""x".captures("\\d")"#), "()");
+    assert_eq!(
+        eval_ok(
+            "",
+            r#"let s = "a1 b2"; s.replace(regex("(?<c>\\w)(\\d)"), "$2$c"); s"#
+        ),
+        r#""1a 2b""#
+    );
+    assert!(eval_err("", r#""x".is_match("(")"#).contains("regex parse error"));
+}
+
+#[test]
+fn regex_captures_of_lines() {
+    let text = "a=1\nb=2\nc\n";
+    assert_eq!(
+        eval_ok(
+            text,
+            r#"data.lines().take(2).captures("(\\w)=(\\d)").map(|c| c["2"]).collect()"#
+        ),
+        r#"["1", "2"]"#
+    );
+    let err = eval_err(
+        text,
+        r#"data.lines().captures(regex("(\\w)=(\\d)")).collect()"#,
+    );
+    assert!(err.contains(r#"does not match "c""#), "{err}");
+    assert!(err.contains("while processing test.dat:3"), "{err}");
+    let err = eval_err(
+        text,
+        r#"data.numbered_lines().captures("(\\w)=(\\d)").collect()"#,
+    );
+    assert!(err.contains(r#"does not match "c" on test.dat:3"#), "{err}");
+}
+
+#[test]
+fn lines_stand_in_for_strings() {
+    let text = "a b\nxa b\n";
+    assert_eq!(
+        eval_ok(text, "let l = data.numbered_lines().next(); [l.contains('b'), \"xa b\".contains(l), \"a\" in l, l in \"xa b\"]"),
+        "[true, true, true, true]"
+    );
+    assert_eq!(
+        eval_ok(text, "let l = data.numbered_lines().next(); [l.split(' '), \"-\" + l, l == \"a b\", \"a b\" != l]"),
+        r#"[["a", "b"], "-a b", true, false]"#
+    );
+    // Both arguments can be lines
+    assert_eq!(
+        eval_ok(
+            text,
+            "let v = data.numbered_lines().collect(); v[1].contains(v[0])"
+        ),
+        "true"
+    );
+    // A string function that changes the string in place changes the line
+    assert_eq!(
+        eval_ok(
+            text,
+            "let l = data.numbered_lines().next(); l.make_upper(); [l.text, l.line_no]"
+        ),
+        r#"["A B", 1]"#
+    );
+    // `switch` only matches real strings
+    assert_eq!(
+        eval_ok(
+            text,
+            r#"let l = data.numbered_lines().next(); switch l.text { "a b" => 1, _ => 2 }"#
+        ),
+        "1"
+    );
+}
+
+#[test]
+fn line_errors_tell_the_line() {
+    let text = "a\nb\nc\n";
+    let err = eval_err(
+        text,
+        r#"let it = data.numbered_lines(); it.next(); let l = it.next(); assert_eq(l, "x")"#,
+    );
+    assert!(err.contains("assert_eq for line test.dat:2"), "{err}");
+    let err = eval_err(
+        text,
+        r#"let it = data.numbered_lines(); it.next(); it.next(); let l = it.next(); assert_eq("x", l)"#,
+    );
+    assert!(err.contains("assert_eq for line test.dat:3"), "{err}");
+    let err = eval_err(text, "let l = data.numbered_lines().next(); l.parse_int()");
+    assert!(err.contains("parse_int for line test.dat:1"), "{err}");
+    let err = eval_err(text, "let l = data.numbered_lines().next(); l[5]");
+    assert!(err.contains("index for line test.dat:1"), "{err}");
+}
+
+#[test]
+fn numbered_lines_count_from_the_start_of_the_file() {
+    let text = "a\nb\n\nc\nd\n";
+    assert_eq!(
+        eval_ok(
+            text,
+            "let it = data.numbered_lines(); it.next(); it.map(|l| l.line_no).collect()"
+        ),
+        "[2, 3, 4, 5]"
+    );
+    assert_eq!(
+        eval_ok(
+            text,
+            "data.next_section(); data.next_section().numbered_lines().map(|l| [l.text, l.line_no]).collect()"
+        ),
+        r#"[["c", 4], ["d", 5]]"#
+    );
+    assert_eq!(
+        eval_ok(
+            text,
+            "let s = data.next_section(); s.lines().next(); s.numbered_lines().next().to_debug()"
+        ),
+        r#""\"b\" @ test.dat:2""#
+    );
+}
+
+#[test]
+fn iterators_are_lazy() {
+    // `take` stops pulling items, so `map` only sees the first two lines
+    assert_eq!(
+        eval_ok(
+            "1\n2\n3\n4\n",
+            "let seen = []; let v = data.lines().map(|l| { seen.push(l); l }).take(2).collect(); [v, seen.len(), data.line_no]"
+        ),
+        r#"[["1", "2"], 2, 2]"#
+    );
+    // Nothing runs until the iterator is used
+    assert_eq!(
+        eval_ok("1\n", "let it = data.lines().map(|l| throw \"ran\"); 1"),
+        "1"
+    );
+    assert_eq!(
+        eval_ok(
+            "1\n2\nx\n",
+            "data.lines().map(|l| parse_int(l)).take_while(|v| v < 2).collect()"
+        ),
+        "[1]"
+    );
+}
+
+#[test]
+fn iterator_errors_propagate() {
+    let text = "1\n2\nx\n4\n";
+    for script in [
+        "data.lines().map(|l| if l == \"x\" { throw \"bad line\" } else { l }).collect()",
+        "data.lines().filter(|l| if l == \"x\" { throw \"bad line\" } else { true }).count()",
+        "data.lines().flat_map(|l| if l == \"x\" { throw \"bad line\" } else { [l] }).last()",
+        "for l in data.lines().map(|l| if l == \"x\" { throw \"bad line\" } else { l }) {}",
+        "data.lines().any(|l| if l == \"x\" { throw \"bad line\" } else { false })",
+    ] {
+        let err = eval_err(text, script);
+        assert!(err.contains("bad line"), "{script}: {err}");
+        assert!(
+            err.contains("while processing test.dat:3"),
+            "{script}: {err}"
+        );
+    }
+    let err = eval_err(text, "data.lines().flat_map(|l| 1).collect()");
+    assert!(err.contains("array, range or iterator"), "{err}");
+    let err = eval_err(text, "data.lines().zip(1).collect()");
+    assert!(err.contains("array, range or iterator"), "{err}");
+}
+
+#[test]
+fn zip_chain_and_flat_map() {
+    let text = "a\nb\nc\n";
+    assert_eq!(
+        eval_ok(text, "data.lines().zip([1, 2]).collect()"),
+        r#"[["a", 1], ["b", 2]]"#
+    );
+    assert_eq!(
+        eval_ok(text, "data.lines().zip(10..20).collect()"),
+        r#"[["a", 10], ["b", 11], ["c", 12]]"#
+    );
+    assert_eq!(
+        eval_ok(text, "data.lines().zip(data.lines()).collect()"),
+        r#"[["a", "b"]]"#
+    );
+    assert_eq!(
+        eval_ok(
+            text,
+            "data.lines().take(1).chain([1]).chain(2..=3).collect()"
+        ),
+        r#"["a", 1, 2, 3]"#
+    );
+    assert_eq!(
+        eval_ok(
+            text,
+            r#"data.lines().take(2).flat_map(|l| [l, l + "!"]).collect()"#
+        ),
+        r#"["a", "a!", "b", "b!"]"#
+    );
+    assert_eq!(
+        eval_ok(
+            text,
+            "data.lines().enumerate().flat_map(|v| 0..v[0]).collect()"
+        ),
+        "[0, 0, 1]"
+    );
+    assert_eq!(
+        eval_ok(text, "data.lines().flat_map(|l| l.chunks(1)).count()"),
+        "3"
+    );
+}
+
+/// `skip` is generic over the item types, like `take`
+#[test]
+fn skip_any_item_type() {
+    let text = "a\nb\nc\n";
+    assert_eq!(
+        eval_ok(text, "data.lines().skip(1).collect()"),
+        r#"["b", "c"]"#
+    );
+    assert_eq!(
+        eval_ok(
+            text,
+            "data.numbered_lines().skip(2).map(|l| l.line_no).collect()"
+        ),
+        "[3]"
+    );
+    assert_eq!(eval_ok(text, "data.lines().skip(5).collect()"), "[]");
+    let err = eval_err(text, "data.lines().skip(-1)");
+    assert!(err.contains("-1"), "{err}");
+}
+
+#[test]
+fn number_bases() {
+    assert_eq!(
+        eval_ok(
+            "",
+            r#"[to_balanced_base(1747, "=-012"), from_balanced_base("1=-0-2", "=-012")]"#
+        ),
+        r#"["1=-0-2", 1747]"#
+    );
+    assert_eq!(
+        eval_ok(
+            "",
+            r#"[to_base(-5, "01"), from_base("-101", "01"), parse_int(" FF", 16)]"#
+        ),
+        r#"["-101", -5, 255]"#
+    );
+    let err = eval_err("", r#"to_balanced_base(1, "01")"#);
+    assert!(
+        err.contains("A balanced base needs at least 3 digits"),
+        "{err}"
+    );
+    let err = eval_err("", r#"parse_int("12", 37)"#);
+    assert!(err.contains("Invalid radix 37"), "{err}");
+}
+
+#[test]
+fn parse_json_and_nested() {
+    assert_eq!(
+        eval_ok("", r#"parse_json("[1, {\"a\": \"b\"}]")"#),
+        r#"[1, #{"a": "b"}]"#
+    );
+    let err = eval_err("", r#"parse_json("[1,")"#);
+    assert!(
+        err.contains("Invalid JSON: EOF while parsing a value at line 1 column 3"),
+        "{err}"
+    );
+    assert_eq!(
+        eval_ok("[1,[2]]\n", "data.lines().next().parse_nested()"),
+        "[1, [2]]"
+    );
+    let err = eval_err("", r#""[1 2]".parse_nested()"#);
+    assert!(
+        err.contains("Expected ',' or ']' at column 4, got '2'"),
+        "{err}"
+    );
+}
+
+#[test]
+fn windows_and_chunks() {
+    assert_eq!(
+        eval_ok("", "[1, 2, 3].windows(2).collect()"),
+        "[[1, 2], [2, 3]]"
+    );
+    assert_eq!(
+        eval_ok("", "[1, 2, 3].chunks(2).collect()"),
+        "[[1, 2], [3]]"
+    );
+    assert_eq!(eval_ok("", "[1, 2].windows(3).count()"), "0");
+    assert_eq!(
+        eval_ok(
+            "",
+            "int_array([1, 2, 3]).windows(2).map(|w| w.to_array()).collect()"
+        ),
+        "[[1, 2], [2, 3]]"
+    );
+    assert_eq!(
+        eval_ok(
+            "",
+            "int_array([1, 2, 3]).chunks(2).map(|w| w.to_array()).collect()"
+        ),
+        "[[1, 2], [3]]"
+    );
+    assert_eq!(
+        eval_ok(
+            "abc\n",
+            "data.blobs().next().windows(2).map(|b| b.as_string()).collect()"
+        ),
+        r#"["ab", "bc"]"#
+    );
+    assert_eq!(
+        eval_ok(
+            "abc\n",
+            "data.blobs().next().chunks(2).map(|b| b.as_string()).collect()"
+        ),
+        r#"["ab", "c"]"#
+    );
+    assert_eq!(
+        eval_ok("", r#""héllo".windows(4).collect()"#),
+        r#"["héll", "éllo"]"#
+    );
+    assert_eq!(
+        eval_ok("", r#""héllo".chunks(2).collect()"#),
+        r#"["hé", "ll", "o"]"#
+    );
+    assert_eq!(eval_ok("", r#""ab".windows(3).count()"#), "0");
+    for script in [
+        "[1].windows(0)",
+        "[1].chunks(0)",
+        "int_array([1]).windows(0)",
+        r#""a".windows(0)"#,
+        r#""a".chunks(0)"#,
+    ] {
+        assert!(
+            eval_err("", script).contains("Length must be positive"),
+            "{script}"
+        );
+    }
+    assert!(eval_err("", "[1].windows(-1)").contains("-1"));
+}
+
+#[test]
+fn peek_line_and_rewind() {
+    let text = "a\r\nb\n\nc\n";
+    assert_eq!(
+        eval_ok(
+            text,
+            "[data.peek_line(), data.peek_line(), data.line_no, data.offset, data.next_line(), data.peek_line(), data.line_no]"
+        ),
+        r#"["a", "a", 0, 0, "a", "b", 1]"#
+    );
+    // The peeked line is read by every kind of read
+    assert_eq!(
+        eval_ok(
+            text,
+            "data.peek_line(); let b = data.blobs().next(); data.peek_line(); [b.as_string(), data.lines().collect(), data.peek_line(), data.offset]"
+        ),
+        r#"["a", ["b", "", "c"], (), 8]"#
+    );
+    assert_eq!(
+        eval_ok(
+            text,
+            "data.peek_line(); let s = data.next_section(); [s.lines().collect(), data.peek_line()]"
+        ),
+        r#"[["a", "b"], "c"]"#
+    );
+    // Random access and rewinding forget the peeked line
+    assert_eq!(
+        eval_ok(
+            text,
+            "data.next_line(); data.peek_line(); let l = data.line(4); [l, data.peek_line(), data.next_line(), data.line_no]"
+        ),
+        r#"["c", "b", "b", 2]"#
+    );
+    assert_eq!(
+        eval_ok(
+            text,
+            "data.next_line(); data.peek_line(); data.rewind(); [data.line_no, data.offset, data.peek_line(), data.next_line()]"
+        ),
+        r#"[0, 0, "a", "a"]"#
+    );
+    assert_eq!(eval_ok("", "data.peek_line()"), "()");
+}
+
+#[test]
+fn random_access() {
+    let text = "a\nbb\n\nc";
+    assert_eq!(
+        eval_ok(
+            text,
+            "data.next_line(); [data.line_count(), data.line(1), data.line(2), data.line(3), data.line(4), data.text(), data.next_line(), data.line_no]"
+        ),
+        r#"[4, "a", "bb", "", "c", "a\nbb\n\nc", "bb", 2]"#
+    );
+    // Lines are numbered like `line_no`, also in a section
+    assert_eq!(
+        eval_ok(
+            text,
+            "data.next_line(); data.next_line(); [data.line(data.line_no), data.line_no]"
+        ),
+        r#"["bb", 2]"#
+    );
+    assert_eq!(
+        eval_ok(
+            text,
+            "data.next_section(); let s = data.next_section(); s.next_line(); [s.line_no, s.line(s.line_no), s.line(4)]"
+        ),
+        r#"[4, "c", "c"]"#
+    );
+    for line_no in [0, 5, -1] {
+        let err = eval_err(text, &format!("data.line({line_no})"));
+        assert!(err.contains(&format!("Invalid index: {line_no}")), "{err}");
+    }
+    let err = eval_err(text, "data.next_section(); data.next_section().line(3)");
+    assert!(err.contains("Invalid index: 3"), "{err}");
+    assert_eq!(
+        eval_ok("", "[data.line_count(), data.text()]"),
+        r#"[0, ""]"#
+    );
+    // Errors after random access still point at the line being processed
+    let err = eval_err(text, "data.next_line(); data.line(4); throw \"stop\"");
+    assert!(err.contains("while processing test.dat:1"), "{err}");
+}
+
+#[test]
+fn compiled_expressions() {
+    assert_eq!(
+        eval_ok(
+            "",
+            r#"let e = compile_expr("a * b - c", ["a", "b", "c"]); [e.apply([2, 3, 4]), e.apply([0, 0, 1])]"#
+        ),
+        "[2, -1]"
+    );
+    assert_eq!(
+        eval_ok(
+            "",
+            r#"compile_expr("x * x", ["x"]).apply(9223372036854775807)"#
+        ),
+        "1"
+    );
+    let err = eval_err(
+        "",
+        r#"compile_expr("x * x", ["x"], #{check_overflow: true}).apply(9223372036854775807)"#,
+    );
+    assert!(
+        err.contains("Overflow in 9223372036854775807 * 9223372036854775807"),
+        "{err}"
+    );
+    let err = eval_err("", r#"compile_expr("a + b", ["a", "b"]).apply([1])"#);
+    assert!(err.contains(r#""a + b" takes 2 values, got 1"#), "{err}");
+    let err = eval_err("", r#"compile_expr("a", ["a"], #{wrap: true})"#);
+    assert!(err.contains(r#"Unknown option "wrap""#), "{err}");
+    let err = eval_err("", r#"compile_expr("a +", ["a"])"#);
+    assert!(err.contains(r#"at the end of "a +""#), "{err}");
+}
+
+/// A line that is not UTF-8 is an error the script can catch, reading goes on after it
+#[test]
+fn read_errors_can_be_caught() {
+    let script = r#"
+        let err = ();
+        try {
+            for l in data.lines() {}
+        } catch (e) {
+            err = e;
+        }
+        let line_no = data.line_no;
+        let next = data.next_line();
+        data.rewind();
+        [err, line_no, next, data.blobs().collect()]
+    "#;
+    assert_eq!(
+        eval_file("tests/fixtures/read_errors.dat", script),
+        Ok(concat!(
+            r#"["Failed to read line 2 of \"tests/fixtures/read_errors.dat\": "#,
+            r#"invalid utf-8 sequence of 1 bytes from index 1", 2, "c", [[61], [62ff], [63]]]"#
+        )
+        .into())
+    );
+}
+
+#[test]
+fn text_columns() {
+    let text = "ab\nabcd\na";
+    assert_eq!(
+        eval_ok("", &format!("{text:?}.columns_up()")),
+        r#"["aaa", "bb", "c", "d"]"#
+    );
+    assert_eq!(
+        eval_ok("", &format!("{text:?}.columns(1, 2)")),
+        r#"["bb", " d"]"#
+    );
+    assert_eq!(
+        eval_ok("", &format!("{text:?}.columns([3, 0])")),
+        r#"[" d", "aaa"]"#
+    );
+    assert_eq!(
+        eval_ok("", &format!("{text:?}.columns_up([2])")),
+        r#"["c"]"#
+    );
+    assert_eq!(
+        eval_ok("", r#""[A]     [B]      \n[C] [D]".field_columns_up(4)"#),
+        r#"[["[C]", "[A]"], ["[D]"], ["", "[B]"]]"#
+    );
+    for script in [r#""ab".columns(0, 0)"#, r#""ab".field_columns(0)"#] {
+        let err = eval_err("", script);
+        assert!(err.contains("Column step must not be 0"), "{err}");
+    }
+    let err = eval_err("", r#""ab".columns(-1, 1)"#);
+    assert!(err.contains("-1"), "{err}");
+}
+
+/// `ints` reads the whole data, wherever the reader is
+#[test]
+fn ints_of_the_whole_data() {
+    let text = "1 2\nx=-3\n4-5\n";
+    assert_eq!(
+        eval_ok(
+            text,
+            "data.next_line(); let all = data.ints(); [all.to_array(), data.next_line()]"
+        ),
+        r#"[[1, 2, -3, 4, 5], "x=-3"]"#
+    );
+    let err = eval_err("1\n99999999999999999999\n", "data.ints()");
+    assert!(
+        err.contains("number too large to fit in target type on test.dat:2"),
+        "{err}"
+    );
+    assert_eq!(
+        eval_ok(
+            "1\n\n2 3\n",
+            "data.sections().map(|s| s.ints().to_array()).collect()"
+        ),
+        "[[1], [2, 3]]"
+    );
+}
+
+#[test]
+fn grids() {
+    let text = "ab\r\nba\r\n\r\nrest\r\n";
+    assert_eq!(
+        eval_ok(
+            text,
+            r#"let g = data.grid(); [g.width, g.height, g.pixels().map(|p| p.as_int()).collect(), data.next_line()]"#
+        ),
+        r#"[2, 2, [97, 98, 98, 97], "rest"]"#
+    );
+    assert_eq!(
+        eval_ok(
+            "S.E\n.#.\n",
+            r##"let m = data.grid(#{".": 0, "#": 1, "S": 0, "E": 0}, "SE#"); let xy = |v| v.map(|p| [p[0], p[1]]); [xy.call(m.S), xy.call(m.E), xy.call(m["#"]), m.grid.pixels().map(|p| p.as_int()).collect()]"##
+        ),
+        "[[[0, 0]], [[2, 0]], [[1, 1]], [0, 0, 0, 0, 1, 0]]"
+    );
+    assert_eq!(
+        eval_ok(
+            "ab\nba\n",
+            r#"let m = data.grid("ab", "ac"); [m.a.map(|p| [p[0], p[1]]), m.c]"#
+        ),
+        "[[[0, 0], [1, 1]], []]"
+    );
+    // Errors tell the line in the file, not the row of the grid
+    let text = "header\n\nabc\nab\n";
+    let err = eval_err(text, "data.next_section(); data.grid()");
+    assert!(
+        err.contains(r#"Line 4 of "test.dat" has 2 characters, expected 3"#),
+        "{err}"
+    );
+    let err = eval_err(text, r#"data.next_section(); data.grid("abc")"#);
+    assert!(
+        err.contains(r#"Line 4 of "test.dat" has 2 characters, expected 3"#),
+        "{err}"
+    );
+    let err = eval_err("..\n.x\n", r#"data.grid(".#")"#);
+    assert!(
+        err.contains(r#"Unexpected 'x' at line 2, column 2 of "test.dat""#),
+        "{err}"
+    );
+    let err = eval_err("", "data.grid()");
+    assert!(err.contains("No rows to load into a grid"), "{err}");
+}
+
+/// Blank lines of any kind and number only ever separate paragraphs
+#[test]
+fn paragraphs() {
+    for text in [
+        "a\nb\n\nc\n",
+        "a\r\nb\r\n\r\nc\r\n",
+        "a\nb\n\n\n\nc\n",
+        "\n\na\nb\n\nc\n",
+        "a\nb\n\nc\n\n",
+        "\r\na\r\nb\r\n\r\n\r\nc\r\n\r\n",
+        "a\nb\n\nc",
+    ] {
+        assert_eq!(
+            eval_ok(text, "data.paragraphs().collect()"),
+            r#"[["a", "b"], ["c"]]"#,
+            "{text:?}"
+        );
+        assert_eq!(
+            eval_ok(text, "data.paragraph_blobs().collect()"),
+            "[[[61], [62]], [[63]]]",
+            "{text:?}"
+        );
+    }
+    for text in ["", "\n", "\r\n\r\n\n"] {
+        assert_eq!(
+            eval_ok(text, "data.paragraphs().collect()"),
+            "[]",
+            "{text:?}"
+        );
+        assert_eq!(
+            eval_ok(text, "data.paragraph_blobs().collect()"),
+            "[]",
+            "{text:?}"
+        );
+    }
+}
+
+/// Sections know their lines in the file, their offsets count in their own text
+#[test]
+fn sections_keep_line_numbers() {
+    let text = "\na\r\nb\r\n\r\n\r\nc\r\n";
+    assert_eq!(
+        eval_ok(
+            text,
+            "data.sections().map(|s| [s.line_no, s.offset, s.lines().collect(), s.line_no, s.offset]).collect()"
+        ),
+        r#"[[1, 0, ["a", "b"], 3, 4], [5, 0, ["c"], 6, 2]]"#
+    );
+    assert_eq!(
+        eval_ok(
+            text,
+            "data.sections().map(|s| s.numbered_lines().map(|l| l.line_no).collect()).collect()"
+        ),
+        "[[2, 3], [6]]"
+    );
+    // An error in a section tells its line in the file
+    let err = eval_err(
+        text,
+        r#"data.sections().map(|s| s.lines().scan("a").collect()).collect()"#,
+    );
+    assert!(err.contains("while processing test.dat:3"), "{err}");
+}
@@ -0,0 +1,58 @@
+use crate::*;
+
+/// Length of a window or chunk, which must be positive
+pub fn slice_len(ctx: &NativeCallContext, len: INT) -> RhaiRes<usize> {
+    match try_from(ctx, len)? {
+        0 => Err(runtime_error(ctx, "Length must be positive")),
+        len => Ok(len),
+    }
+}
+
+/// Lazily map each run of `len` consecutive items, overlapping by all but one item
+pub fn map_windows<T: 'static, R: 'static>(
+    items: Vec<T>,
+    len: usize,
+    f: impl Fn(&[T]) -> R + 'static,
+) -> DynIterator<R> {
+    let count = (items.len() + 1).saturating_sub(len);
+    DynIterator::new((0..count).map(move |i| f(&items[i..i + len])))
+}
+
+/// Lazily map `len` items at a time, the last chunk may be shorter
+pub fn map_chunks<T: 'static, R: 'static>(
+    items: Vec<T>,
+    len: usize,
+    f: impl Fn(&[T]) -> R + 'static,
+) -> DynIterator<R> {
+    let count = items.len().div_ceil(len);
+    DynIterator::new((0..count).map(move |i| f(&items[i * len..items.len().min((i + 1) * len)])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect<T>(it: DynIterator<T>) -> Vec<T> {
+        it.map(|v| v.unwrap()).collect()
+    }
+
+    #[test]
+    fn windows() {
+        let windows = |len| collect(map_windows(vec![1, 2, 3], len, <[_]>::to_vec));
+        assert_eq!(windows(1), [vec![1], vec![2], vec![3]]);
+        assert_eq!(windows(2), [vec![1, 2], vec![2, 3]]);
+        assert_eq!(windows(3), [vec![1, 2, 3]]);
+        assert!(windows(4).is_empty());
+        assert!(collect(map_windows(Vec::<INT>::new(), 1, <[_]>::to_vec)).is_empty());
+    }
+
+    #[test]
+    fn chunks() {
+        let chunks = |len| collect(map_chunks(vec![1, 2, 3], len, <[_]>::to_vec));
+        assert_eq!(chunks(1), [vec![1], vec![2], vec![3]]);
+        assert_eq!(chunks(2), [vec![1, 2], vec![3]]);
+        assert_eq!(chunks(3), [vec![1, 2, 3]]);
+        assert_eq!(chunks(4), [vec![1, 2, 3]]);
+        assert!(collect(map_chunks(Vec::<INT>::new(), 2, <[_]>::to_vec)).is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+use crate::*;
+
+pub use self::text_block::*;
+
+/// The lines of a multi-line string as characters, ordered bottom-up for the `_up`
+/// functions. Shorter lines read as if they were padded with spaces.
+struct TextBlock {
+    lines: Vec<Vec<char>>,
+    width: usize,
+    up: bool,
+}
+
+impl TextBlock {
+    fn new(text: &str, up: bool) -> Self {
+        let mut lines: Vec<Vec<char>> = text.lines().map(|l| l.chars().collect()).collect();
+        if up {
+            lines.reverse();
+        }
+        let width = lines.iter().map(Vec::len).max().unwrap_or(0);
+        Self { lines, width, up }
+    }
+
+    /// The characters of column `x` without the padding at its end. Read bottom-up the
+    /// blanks below the drawing are dropped as well, they are where shorter lines end.
+    fn column(&self, x: usize) -> String {
+        let col: String = self
+            .lines
+            .iter()
+            .map(|l| l.get(x).copied().unwrap_or(' '))
+            .collect();
+        match self.up {
+            true => col.trim(),
+            false => col.trim_end(),
+        }
+        .to_string()
+    }
+
+    fn columns(&self, offset: usize, step: usize) -> Vec<String> {
+        (offset..self.width)
+            .step_by(step)
+            .map(|x| self.column(x))
+            .collect()
+    }
+
+    /// Cut each line into fields `width` characters wide and return the trimmed fields of
+    /// each column. The empty fields at the end of a column are left out, and so are the
+    /// columns at the end that have no fields at all.
+    fn fields(&self, width: usize) -> Vec<Vec<String>> {
+        let mut ret: Vec<Vec<String>> = (0..self.width)
+            .step_by(width)
+            .map(|start| {
+                let mut col: Vec<String> = self
+                    .lines
+                    .iter()
+                    .map(|l| {
+                        let field: String = l.iter().skip(start).take(width).collect();
+                        field.trim().to_string()
+                    })
+                    .collect();
+                while col.last().is_some_and(String::is_empty) {
+                    col.pop();
+                }
+                col
+            })
+            .collect();
+        while ret.last().is_some_and(Vec::is_empty) {
+            ret.pop();
+        }
+        ret
+    }
+}
+
+fn column_step(ctx: &NativeCallContext, step: INT) -> RhaiRes<usize> {
+    match try_from(ctx, step)? {
+        0 => Err(runtime_error(ctx, "Column step must not be 0")),
+        step => Ok(step),
+    }
+}
+
+fn strings(strings: Vec<String>) -> Array {
+    strings.into_iter().map(Dynamic::from).collect()
+}
+
+fn every(ctx: &NativeCallContext, text: &str, offset: INT, step: INT, up: bool) -> RhaiRes<Array> {
+    let offset = try_from(ctx, offset)?;
+    let step = column_step(ctx, step)?;
+    Ok(strings(TextBlock::new(text, up).columns(offset, step)))
+}
+
+fn at(ctx: &NativeCallContext, text: &str, offsets: Array, up: bool) -> RhaiRes<Array> {
+    let block = TextBlock::new(text, up);
+    offsets
+        .into_iter()
+        .map(|x| {
+            let x = x
+                .as_int()
+                .map_err(|e| mismatching_data_type(ctx, "integer", e))?;
+            Ok(block.column(try_from(ctx, x)?).into())
+        })
+        .collect()
+}
+
+fn fields(ctx: &NativeCallContext, text: &str, width: INT, up: bool) -> RhaiRes<Array> {
+    let width = column_step(ctx, width)?;
+    Ok(TextBlock::new(text, up)
+        .fields(width)
+        .into_iter()
+        .map(|col| strings(col).into())
+        .collect())
+}
+
+#[export_module]
+mod text_block {
+    use super::{at, every, fields, strings};
+
+    /// All the columns of a block of lines as strings read top-down
+    pub fn columns(text: &str) -> Array {
+        strings(TextBlock::new(text, false).columns(0, 1))
+    }
+
+    /// Every `step`th column starting at `offset`
+    #[rhai_fn(name = "columns", return_raw)]
+    pub fn columns_every(
+        ctx: NativeCallContext,
+        text: &str,
+        offset: INT,
+        step: INT,
+    ) -> RhaiRes<Array> {
+        every(&ctx, text, offset, step, false)
+    }
+
+    /// The columns at each of the `offsets`
+    #[rhai_fn(name = "columns", return_raw)]
+    pub fn columns_at(ctx: NativeCallContext, text: &str, offsets: Array) -> RhaiRes<Array> {
+        at(&ctx, text, offsets, false)
+    }
+
+    /// All the columns of a block of lines as strings read bottom-up, like the stacks of
+    /// a drawing
+    pub fn columns_up(text: &str) -> Array {
+        strings(TextBlock::new(text, true).columns(0, 1))
+    }
+
+    #[rhai_fn(name = "columns_up", return_raw)]
+    pub fn columns_up_every(
+        ctx: NativeCallContext,
+        text: &str,
+        offset: INT,
+        step: INT,
+    ) -> RhaiRes<Array> {
+        every(&ctx, text, offset, step, true)
+    }
+
+    #[rhai_fn(name = "columns_up", return_raw)]
+    pub fn columns_up_at(ctx: NativeCallContext, text: &str, offsets: Array) -> RhaiRes<Array> {
+        at(&ctx, text, offsets, true)
+    }
+
+    /// Cut the lines into fields `width` characters wide and return an array of the
+    /// trimmed fields for each column, read top-down
+    #[rhai_fn(return_raw)]
+    pub fn field_columns(ctx: NativeCallContext, text: &str, width: INT) -> RhaiRes<Array> {
+        fields(&ctx, text, width, false)
+    }
+
+    /// Like `field_columns`, but each column is read bottom-up
+    #[rhai_fn(return_raw)]
+    pub fn field_columns_up(ctx: NativeCallContext, text: &str, width: INT) -> RhaiRes<Array> {
+        fields(&ctx, text, width, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STACKS: &str = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n";
+
+    #[test]
+    fn ragged_edges() {
+        let text = "ab\nabcd\na";
+        assert_eq!(
+            TextBlock::new(text, false).columns(0, 1),
+            ["aaa", "bb", " c", " d"]
+        );
+        assert_eq!(
+            TextBlock::new(text, true).columns(0, 1),
+            ["aaa", "bb", "c", "d"]
+        );
+        assert!(TextBlock::new("", true).columns(0, 1).is_empty());
+    }
+
+    #[test]
+    fn every_step_columns() {
+        let block = TextBlock::new(STACKS, true);
+        assert_eq!(block.columns(1, 4), ["1ZN", "2MCD", "3P"]);
+        assert_eq!(block.columns(13, 4), Vec::<String>::new());
+        let block = TextBlock::new(STACKS, false);
+        assert_eq!(block.columns(1, 4), [" NZ1", "DCM2", "  P3"]);
+    }
+
+    #[test]
+    fn field_columns_are_trimmed() {
+        // The trailing spaces of the first line do not make a fourth column
+        let text = "[A]     [B]      \n[C] [D]\n";
+        let down = TextBlock::new(text, false).fields(4);
+        assert_eq!(down, [vec!["[A]", "[C]"], vec!["", "[D]"], vec!["[B]"]]);
+        let up = TextBlock::new(text, true).fields(4);
+        assert_eq!(up, [vec!["[C]", "[A]"], vec!["[D]"], vec!["", "[B]"]]);
+        assert_eq!(TextBlock::new(STACKS, true).fields(4).len(), 3);
+        assert!(TextBlock::new("   \n", false).fields(4).is_empty());
+    }
+}